@@ -0,0 +1,37 @@
+/*
+* Copyright 2017-2019 Michal Mauser
+*
+* This program is free software: you can redistribute it and/or modify
+* it under the terms of the GNU Affero General Public License as published by
+* the Free Software Foundation, either version 3 of the License, or
+* (at your option) any later version.
+*
+* This program is distributed in the hope that it will be useful,
+* but WITHOUT ANY WARRANTY; without even the implied warranty of
+* MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+* GNU Affero General Public License for more details.
+*
+* You should have received a copy of the GNU Affero General Public License
+* along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+extern crate cbindgen;
+
+use std::env;
+
+// Regenerates the C header from the exported functions and `#[repr(C)]` types.
+fn main() {
+    let crate_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    // the exported items may come from any module
+    for path in &["cbindgen.toml", "src"] {
+        println!("cargo:rerun-if-changed={}", path);
+    }
+
+    let config = cbindgen::Config::from_root_or_default(&crate_dir);
+    cbindgen::Builder::new()
+        .with_crate(&crate_dir)
+        .with_config(config)
+        .generate()
+        .expect("Unable to generate the C header.")
+        .write_to_file("include/resource_mng.h");
+}
@@ -0,0 +1,924 @@
+#ifndef RESOURCE_MNG_H
+#define RESOURCE_MNG_H
+
+/* Generated by build.rs from the Rust sources, do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Scarcity of a material whose demand is in balance with its supply.
+ */
+#define EQUILIBRIUM 50.0
+
+/**
+ * Subscribers are warned when less than this share of what a material ever had is left.
+ */
+#define DEPLETION_WARNING 0.1
+
+#define VERBOSITY_QUIET 0
+
+#define VERBOSITY_RESULTS 1
+
+#define VERBOSITY_INNER 2
+
+#define VERBOSITY_FAILURES 3
+
+/**
+ * Version written by `save`. Raised with every change of the saved layout, keeping the
+ * old binary layout in `legacy` and a file of it in tests/data.
+ */
+#define FORMAT_VERSION 4
+
+typedef enum OrderState {
+  OrderState_Queued,
+  /**
+   * Sub-products needed for the order or the order itself are being manufactured.
+   */
+  OrderState_InProduction,
+  OrderState_Finished,
+  OrderState_Rejected,
+  OrderState_Cancelled,
+} OrderState;
+
+/**
+ * Whose reservations an order may use when there is not enough unreserved supply.
+ */
+typedef enum Preemption {
+  Preemption_Never,
+  /**
+   * Those of orders with a lower priority, the lowest first.
+   */
+  Preemption_HigherPriority,
+} Preemption;
+
+typedef enum ComponentKind {
+  ComponentKind_Material,
+  ComponentKind_Product,
+} ComponentKind;
+
+typedef enum Format {
+  Format_Json,
+  /**
+   * Compact, but not meant to be read by people.
+   */
+  Format_Binary,
+} Format;
+
+/**
+ * Kind of a `CSubstitution`, named like the `Substitution` it stands for.
+ */
+typedef enum SubstitutionKind {
+  SubstitutionKind_Never,
+  SubstitutionKind_Any,
+  SubstitutionKind_WithinEfficiency,
+  SubstitutionKind_Only,
+} SubstitutionKind;
+
+/**
+ * Reasons a call into the library can fail.
+ *
+ * The C functions return the discriminant of the error, so the numbers are the same
+ * for every function. `0` means success; codes `1` to `3` are the non-zero
+ * outcomes of an accepted order (see `OrderOutcome`).
+ *
+ * | code | error                 |
+ * |------|-----------------------|
+ * | 10   | `InvalidName`         |
+ * | 11   | `ZeroAmount`          |
+ * | 12   | `NoSuchMaterial`      |
+ * | 13   | `NoSuchProduct`       |
+ * | 14   | `NoSuchVariant`       |
+ * | 15   | `NoSuchOrder`         |
+ * | 16   | `DuplicateMaterial`   |
+ * | 17   | `DuplicateProduct`    |
+ * | 18   | `CircularDependency`  |
+ * | 19   | `InUse`               |
+ * | 20   | `LastVariant`         |
+ * | 21   | `Retired`             |
+ * | 22   | `NotQueued`           |
+ * | 23   | `Io`                  |
+ * | 24   | `InvalidFormat`       |
+ * | 25   | `UnsupportedVersion`  |
+ * | 26   | `InvalidReference`    |
+ * | 27   | `NoSuchSubscriber`    |
+ * | 28   | `NoSuchScarcityModel` |
+ * | 29   | `InsufficientSupply`  |
+ * | 30   | `StaleReading`        |
+ * | 31   | `PastTime`            |
+ * | 32   | `NoSuchWorkstation`   |
+ * | 33   | `InvalidPriority`     |
+ * | 255  | `Internal`            |
+ */
+enum ResourceMngError
+#if defined(__cplusplus) || __STDC_VERSION__ >= 202311L
+  : uint8_t
+#endif // defined(__cplusplus) || __STDC_VERSION__ >= 202311L
+ {
+  /**
+   * Names cannot be empty or contain only white spaces; no names, descriptions or
+   * units can contain NUL characters.
+   */
+  ResourceMngError_InvalidName = 10,
+  /**
+   * Supplies and amounts must be finite and greater than zero; set supplies, deposits
+   * and times may be zero.
+   */
+  ResourceMngError_ZeroAmount = 11,
+  ResourceMngError_NoSuchMaterial = 12,
+  ResourceMngError_NoSuchProduct = 13,
+  ResourceMngError_NoSuchVariant = 14,
+  ResourceMngError_NoSuchOrder = 15,
+  ResourceMngError_DuplicateMaterial = 16,
+  ResourceMngError_DuplicateProduct = 17,
+  /**
+   * The component consists, even indirectly, of the product it would be added to.
+   */
+  ResourceMngError_CircularDependency = 18,
+  /**
+   * Remove the variants using it first or remove them along with it.
+   */
+  ResourceMngError_InUse = 19,
+  /**
+   * Remove the product instead.
+   */
+  ResourceMngError_LastVariant = 20,
+  /**
+   * Retired materials, products and variants cannot be ordered.
+   */
+  ResourceMngError_Retired = 21,
+  /**
+   * The order is not waiting in the production queue anymore.
+   */
+  ResourceMngError_NotQueued = 22,
+  /**
+   * Reading or writing a saved instance failed.
+   */
+  ResourceMngError_Io = 23,
+  /**
+   * The saved instance cannot be parsed.
+   */
+  ResourceMngError_InvalidFormat = 24,
+  /**
+   * The saved instance comes from a newer version of the library.
+   */
+  ResourceMngError_UnsupportedVersion = 25,
+  /**
+   * The saved instance refers to a missing material, product, variant or order.
+   */
+  ResourceMngError_InvalidReference = 26,
+  ResourceMngError_NoSuchSubscriber = 27,
+  /**
+   * No scarcity model of the name has been added to the instance.
+   */
+  ResourceMngError_NoSuchScarcityModel = 28,
+  /**
+   * Less of the material is in supply than is to be removed.
+   */
+  ResourceMngError_InsufficientSupply = 29,
+  /**
+   * The supply reading is older than the last reading or change of the supply.
+   */
+  ResourceMngError_StaleReading = 30,
+  /**
+   * The simulation time can only move forward.
+   */
+  ResourceMngError_PastTime = 31,
+  ResourceMngError_NoSuchWorkstation = 32,
+  /**
+   * Priorities must be below the number of priority levels, of which there is at least one;
+   * weighted fair scheduling needs a weight for every level.
+   */
+  ResourceMngError_InvalidPriority = 33,
+  /**
+   * The library panicked; the instance may be left inconsistent.
+   */
+  ResourceMngError_Internal = 255,
+};
+#ifndef __cplusplus
+#if __STDC_VERSION__ >= 202311L
+typedef enum ResourceMngError ResourceMngError;
+#else
+typedef uint8_t ResourceMngError;
+#endif // __STDC_VERSION__ >= 202311L
+#endif // __cplusplus
+
+/**
+ * Outcome of an accepted order; the discriminant is what the C functions return.
+ */
+enum OrderOutcome
+#if defined(__cplusplus) || __STDC_VERSION__ >= 202311L
+  : uint8_t
+#endif // defined(__cplusplus) || __STDC_VERSION__ >= 202311L
+ {
+  /**
+   * Delivered from the finished goods inventory as a whole.
+   */
+  OrderOutcome_Delivered = 0,
+  OrderOutcome_Queued = 1,
+  /**
+   * Queued, but the materials are not available at the moment.
+   */
+  OrderOutcome_NotAvailable = 2,
+  /**
+   * Queued, but some material is scarce, whether or not it is available.
+   */
+  OrderOutcome_Scarce = 3,
+};
+#ifndef __cplusplus
+#if __STDC_VERSION__ >= 202311L
+typedef enum OrderOutcome OrderOutcome;
+#else
+typedef uint8_t OrderOutcome;
+#endif // __STDC_VERSION__ >= 202311L
+#endif // __cplusplus
+
+typedef struct Instance Instance;
+
+/**
+ * `data` is zeroed when `is_some` is false.
+ */
+typedef struct COption_usize {
+  bool is_some;
+  size_t data;
+} COption_usize;
+
+/**
+ * `data` is zeroed when `is_some` is false.
+ */
+typedef struct COption_f64 {
+  bool is_some;
+  double data;
+} COption_f64;
+
+/**
+ * How an order may be fulfilled besides in one piece from a single variant.
+ */
+typedef struct OrderOptions {
+  /**
+   * Deliver what can be made now in parts and keep the rest queued.
+   */
+  bool partial;
+  /**
+   * Make the order from several variants when no single one can cover it, as far as
+   * the substitution allows.
+   */
+  bool split;
+  /**
+   * Priority level to queue the order at instead of the product's.
+   */
+  struct COption_usize priority;
+  /**
+   * Time the order should be finished by; within a level, orders with the earliest
+   * due date go first and those without one last.
+   */
+  struct COption_f64 due;
+} OrderOptions;
+
+typedef struct OrderStatus {
+  enum OrderState state;
+  /**
+   * Priority queue of a queued order.
+   */
+  size_t priority;
+  /**
+   * Position in the priority queue of a queued order.
+   */
+  size_t position;
+  /**
+   * Completion time of an order being manufactured.
+   */
+  double completes_at;
+} OrderStatus;
+
+/**
+ * `data` is zeroed when `is_some` is false.
+ */
+typedef struct COption_OrderStatus {
+  bool is_some;
+  struct OrderStatus data;
+} COption_OrderStatus;
+
+/**
+ * How much of its capacity a workstation uses.
+ */
+typedef struct Utilisation {
+  size_t workstation_id;
+  double capacity;
+  /**
+   * Work complexity started in the current unit of time.
+   */
+  double used;
+  /**
+   * Share of the capacity used in the current unit of time, above 1 for orders larger than the capacity.
+   */
+  double utilisation;
+  /**
+   * Share of the capacity used since the workstation was added.
+   */
+  double average;
+} Utilisation;
+
+/**
+ * `data` is zeroed when `is_some` is false.
+ */
+typedef struct COption_Utilisation {
+  bool is_some;
+  struct Utilisation data;
+} COption_Utilisation;
+
+/**
+ * Supply reading of a material compared with what the library expected from its own ledger.
+ */
+typedef struct Reconciliation {
+  size_t material_id;
+  double timestamp;
+  double reading;
+  /**
+   * Supply before the reading: the previous reading plus deltas and extraction, minus consumption.
+   */
+  double expected;
+  /**
+   * Consumed by manufacturing since the previous reading.
+   */
+  double consumed;
+  /**
+   * Differences of all readings so far; losses are negative.
+   */
+  double total_discrepancy;
+} Reconciliation;
+
+/**
+ * `data` is zeroed when `is_some` is false.
+ */
+typedef struct COption_Reconciliation {
+  bool is_some;
+  struct Reconciliation data;
+} COption_Reconciliation;
+
+/**
+ * An order that was still queued or being manufactured when its due date passed.
+ */
+typedef struct MissedDeadline {
+  size_t order_id;
+  size_t product_id;
+  size_t user_id;
+  double due;
+} MissedDeadline;
+
+/**
+ * `data` is zeroed when `is_some` is false.
+ */
+typedef struct COption_MissedDeadline {
+  bool is_some;
+  struct MissedDeadline data;
+} COption_MissedDeadline;
+
+/**
+ * What happened to the material reserved for an order.
+ */
+typedef struct Reservation {
+  size_t order_id;
+  size_t material_id;
+  /**
+   * Priority of the order when it reserved the material.
+   */
+  size_t priority;
+  /**
+   * Still held for the order.
+   */
+  double reserved;
+  /**
+   * Used by the order, reserved or not.
+   */
+  double consumed;
+  /**
+   * Given back when the order was cancelled, made from another variant or preempted.
+   */
+  double released;
+} Reservation;
+
+/**
+ * `data` is zeroed when `is_some` is false.
+ */
+typedef struct COption_Reservation {
+  bool is_some;
+  struct Reservation data;
+} COption_Reservation;
+
+typedef struct Component {
+  enum ComponentKind kind;
+  size_t id;
+  double amount;
+  double scarcity_cache;
+} Component;
+
+/**
+ * `data` is zeroed when `is_some` is false.
+ */
+typedef struct COption_Component {
+  bool is_some;
+  struct Component data;
+} COption_Component;
+
+typedef struct FinishedOrder {
+  size_t order_id;
+  size_t product_id;
+  double product_amount;
+  size_t preferred_variant;
+  size_t user_id;
+  bool allow_substitution;
+} FinishedOrder;
+
+/**
+ * `data` is zeroed when `is_some` is false.
+ */
+typedef struct COption_FinishedOrder {
+  bool is_some;
+  struct FinishedOrder data;
+} COption_FinishedOrder;
+
+/**
+ * `Substitution` for C: `percent` is read for `WithinEfficiency`, the `variant_count`
+ * ids at `variant_ids` for `Only`.
+ */
+typedef struct CSubstitution {
+  enum SubstitutionKind kind;
+  double percent;
+  const size_t *variant_ids;
+  size_t variant_count;
+} CSubstitution;
+
+typedef enum Notification_Tag {
+  /**
+   * An order, including sub-orders to stock, was put into the production queue.
+   */
+  Notification_OrderQueued,
+  /**
+   * The variant of the product the order is going to be manufactured from.
+   */
+  Notification_VariantSelected,
+  Notification_ProductManufactured,
+  /**
+   * Manufactured or delivered from stock, possibly in parts.
+   */
+  Notification_OrderFinished,
+  /**
+   * Demand for the material has just outgrown the equilibrium with its supply.
+   */
+  Notification_MaterialScarce,
+  /**
+   * Supply and deposit of the material have fallen below `DEPLETION_WARNING` of what it ever had.
+   */
+  Notification_MaterialDepleting,
+  /**
+   * The order was not finished by its due date.
+   */
+  Notification_DeadlineMissed,
+} Notification_Tag;
+
+typedef struct Notification_OrderQueued_Body {
+  size_t order_id;
+  size_t product_id;
+  double amount;
+  size_t priority;
+  bool for_stock;
+} Notification_OrderQueued_Body;
+
+typedef struct Notification_VariantSelected_Body {
+  size_t order_id;
+  size_t product_id;
+  size_t variant_id;
+  size_t preferred_variant;
+} Notification_VariantSelected_Body;
+
+typedef struct Notification_ProductManufactured_Body {
+  size_t order_id;
+  size_t product_id;
+  size_t variant_id;
+  double amount;
+} Notification_ProductManufactured_Body;
+
+typedef struct Notification_OrderFinished_Body {
+  size_t order_id;
+  size_t product_id;
+  double amount;
+  size_t user_id;
+} Notification_OrderFinished_Body;
+
+typedef struct Notification_MaterialScarce_Body {
+  size_t material_id;
+  double scarcity;
+} Notification_MaterialScarce_Body;
+
+typedef struct Notification_MaterialDepleting_Body {
+  size_t material_id;
+  double remaining;
+} Notification_MaterialDepleting_Body;
+
+typedef struct Notification_DeadlineMissed_Body {
+  size_t order_id;
+  size_t product_id;
+  double due;
+} Notification_DeadlineMissed_Body;
+
+typedef struct Notification {
+  Notification_Tag tag;
+  union {
+    Notification_OrderQueued_Body order_queued;
+    Notification_VariantSelected_Body variant_selected;
+    Notification_ProductManufactured_Body product_manufactured;
+    Notification_OrderFinished_Body order_finished;
+    Notification_MaterialScarce_Body material_scarce;
+    Notification_MaterialDepleting_Body material_depleting;
+    Notification_DeadlineMissed_Body deadline_missed;
+  };
+} Notification;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+struct Instance *init(void);
+
+/**
+ * Frees an instance returned by `init` or `clone_instance`. Null is ignored.
+ */
+void destroy(struct Instance *instance);
+
+/**
+ * Clears all data of the instance but keeps its settings. Null is ignored.
+ */
+void reset(struct Instance *instance);
+
+/**
+ * Deep copy of the instance, e.g. to try out orders without affecting the original.
+ * Returns null for null; free the copy with `destroy`.
+ */
+struct Instance *clone_instance(const struct Instance *instance);
+
+/**
+ * The id of the order is written to `order_id` even if the order gets rejected.
+ */
+uint8_t order_product(struct Instance *instance,
+                      size_t id,
+                      double amount,
+                      size_t variant_id,
+                      size_t user_id,
+                      bool allow_substitution,
+                      size_t *order_id);
+
+/**
+ * Like `order_product`, see `OrderOptions`.
+ */
+uint8_t order_product_with(struct Instance *instance,
+                           size_t id,
+                           double amount,
+                           size_t variant_id,
+                           size_t user_id,
+                           bool allow_substitution,
+                           struct OrderOptions options,
+                           size_t *order_id);
+
+/**
+ * The id of the order is written to `order_id` even if the order gets rejected.
+ */
+uint8_t produce_to_stock(struct Instance *instance,
+                         size_t id,
+                         double amount,
+                         size_t variant_id,
+                         bool allow_substitution,
+                         size_t *order_id);
+
+uint8_t cancel_order(struct Instance *instance, size_t order_id);
+
+struct COption_OrderStatus get_order_status(const struct Instance *instance, size_t order_id);
+
+void process_queue(struct Instance *instance);
+
+uint8_t update_supply(struct Instance *instance, size_t id, double amount);
+
+/**
+ * The id of the new variant is written to `id`.
+ */
+uint8_t add_product_variant(struct Instance *instance,
+                            size_t product_id,
+                            size_t material_id,
+                            double material_amount,
+                            double work_complexity,
+                            size_t *id);
+
+uint8_t add_variant_material(struct Instance *instance,
+                             size_t product_id,
+                             size_t variant_id,
+                             size_t material_id,
+                             double material_amount);
+
+uint8_t add_variant_product(struct Instance *instance,
+                            size_t product_id,
+                            size_t variant_id,
+                            size_t component_id,
+                            double amount);
+
+uint8_t remove_material(struct Instance *instance, size_t id, bool cascade);
+
+uint8_t remove_product(struct Instance *instance, size_t id, bool cascade);
+
+uint8_t remove_product_variant(struct Instance *instance, size_t product_id, size_t variant_id);
+
+uint8_t retire_material(struct Instance *instance, size_t id);
+
+uint8_t retire_product(struct Instance *instance, size_t id);
+
+uint8_t retire_product_variant(struct Instance *instance, size_t product_id, size_t variant_id);
+
+uint8_t update_material_deposit_size(struct Instance *instance, size_t id, double size);
+
+uint8_t set_material_regeneration(struct Instance *instance,
+                                  size_t id,
+                                  double rate,
+                                  double capacity);
+
+uint8_t clear_material_regeneration(struct Instance *instance, size_t id);
+
+uint8_t advance_time(struct Instance *instance, double duration);
+
+void tick(struct Instance *instance);
+
+uint8_t advance_to(struct Instance *instance, double time);
+
+double get_time(const struct Instance *instance);
+
+uint8_t set_time_per_complexity(struct Instance *instance, double time);
+
+uint8_t set_variant_duration(struct Instance *instance,
+                             size_t product_id,
+                             size_t variant_id,
+                             double duration);
+
+uint8_t clear_variant_duration(struct Instance *instance, size_t product_id, size_t variant_id);
+
+/**
+ * `id` receives the id of the new workstation.
+ */
+uint8_t add_workstation(struct Instance *instance, double capacity, size_t *id);
+
+uint8_t set_workstation_capacity(struct Instance *instance, size_t id, double capacity);
+
+uint8_t assign_workstation(struct Instance *instance,
+                           size_t product_id,
+                           size_t variant_id,
+                           size_t workstation_id);
+
+uint8_t unassign_workstation(struct Instance *instance, size_t product_id, size_t variant_id);
+
+struct COption_Utilisation get_workstation_utilisation(const struct Instance *instance, size_t id);
+
+size_t get_in_production_count(const struct Instance *instance);
+
+uint8_t add_supply(struct Instance *instance, size_t id, double amount);
+
+uint8_t remove_supply(struct Instance *instance, size_t id, double amount);
+
+uint8_t read_supply(struct Instance *instance, size_t id, double amount, double timestamp);
+
+/**
+ * The last reading of the material; nothing before the first one.
+ */
+struct COption_Reconciliation get_reconciliation(const struct Instance *instance, size_t id);
+
+/**
+ * `extracted` receives the amount actually moved to the supply.
+ */
+uint8_t extract_material(struct Instance *instance, size_t id, double amount, double *extracted);
+
+size_t get_material_count(const struct Instance *instance);
+
+struct COption_f64 get_material_demand(const struct Instance *instance, size_t id);
+
+struct COption_f64 get_material_supply(const struct Instance *instance, size_t id);
+
+size_t get_missed_deadline_count(const struct Instance *instance);
+
+struct COption_MissedDeadline get_missed_deadline(const struct Instance *instance, size_t index);
+
+size_t get_priority_levels(const struct Instance *instance);
+
+uint8_t set_priority_levels(struct Instance *instance, size_t levels);
+
+uint8_t set_strict_scheduling(struct Instance *instance);
+
+uint8_t set_aging_scheduling(struct Instance *instance, double interval);
+
+struct COption_f64 get_material_reserved(const struct Instance *instance, size_t id);
+
+struct COption_f64 get_available_to_promise(const struct Instance *instance, size_t id);
+
+struct COption_Reservation get_reservation(const struct Instance *instance,
+                                           size_t order_id,
+                                           size_t material_id);
+
+void set_preemption(struct Instance *instance, enum Preemption preemption);
+
+struct COption_f64 get_material_deposit_size(const struct Instance *instance, size_t id);
+
+struct COption_f64 get_material_consumed(const struct Instance *instance, size_t id);
+
+struct COption_f64 get_material_scarcity(const struct Instance *instance, size_t id);
+
+size_t get_product_count(const struct Instance *instance);
+
+struct COption_f64 get_product_supply(const struct Instance *instance, size_t id);
+
+struct COption_f64 get_product_demand(const struct Instance *instance, size_t id);
+
+struct COption_usize get_product_priority(const struct Instance *instance, size_t id);
+
+struct COption_usize get_variant_component_count(const struct Instance *instance,
+                                                 size_t product_id,
+                                                 size_t variant_id);
+
+struct COption_Component get_variant_component(const struct Instance *instance,
+                                               size_t product_id,
+                                               size_t variant_id,
+                                               size_t index);
+
+/**
+ * The first component of the variant.
+ */
+struct COption_Component get_product_variant(const struct Instance *instance,
+                                             size_t product_id,
+                                             size_t variant_id);
+
+struct COption_FinishedOrder get_next_finished(struct Instance *instance);
+
+size_t get_finished_count(const struct Instance *instance);
+
+size_t get_queue_len(const struct Instance *instance);
+
+uint8_t tst_set_product_supply(struct Instance *instance, size_t id, double count);
+
+/**
+ * # Safety
+ * `name` must be null or point to a null-terminated string.
+ */
+uint8_t add_material(struct Instance *instance, const char *name, double supply, size_t *id);
+
+/**
+ * # Safety
+ * `name` must be null or point to a null-terminated string.
+ */
+uint8_t add_product(struct Instance *instance,
+                    const char *name,
+                    size_t material_id,
+                    double material_amount,
+                    size_t priority,
+                    double work_complexity,
+                    size_t *id);
+
+/**
+ * # Safety
+ * `description` must be null or point to a null-terminated string.
+ */
+uint8_t set_material_description(struct Instance *instance, size_t id, const char *description);
+
+/**
+ * # Safety
+ * `unit` must be null or point to a null-terminated string.
+ */
+uint8_t set_material_unit(struct Instance *instance, size_t id, const char *unit);
+
+/**
+ * Selects the scarcity model of the given name for the material, the default one for null.
+ *
+ * # Safety
+ * `name` must be null or point to a null-terminated string.
+ */
+uint8_t set_scarcity_model(struct Instance *instance, size_t material_id, const char *name);
+
+/**
+ * # Safety
+ * `description` must be null or point to a null-terminated string.
+ */
+uint8_t set_product_description(struct Instance *instance, size_t id, const char *description);
+
+/**
+ * # Safety
+ * `name` must be null or point to a null-terminated string.
+ */
+struct COption_usize find_material(const struct Instance *instance, const char *name);
+
+/**
+ * # Safety
+ * `name` must be null or point to a null-terminated string.
+ */
+struct COption_usize find_product(const struct Instance *instance, const char *name);
+
+/**
+ * Null if there is no such material.
+ */
+const char *get_material_name(const struct Instance *instance, size_t id);
+
+/**
+ * Null if there is no such material or it has no description.
+ */
+const char *get_material_description(const struct Instance *instance, size_t id);
+
+/**
+ * Null if there is no such material or it has no unit.
+ */
+const char *get_material_unit(const struct Instance *instance, size_t id);
+
+/**
+ * Null if there is no such product.
+ */
+const char *get_product_name(const struct Instance *instance, size_t id);
+
+/**
+ * Null if there is no such product or it has no description.
+ */
+const char *get_product_description(const struct Instance *instance, size_t id);
+
+/**
+ * Saves the instance to a file, replacing it if it exists.
+ *
+ * # Safety
+ * `path` must be null or point to a null-terminated string.
+ */
+uint8_t save_instance(const struct Instance *instance, const char *path, enum Format format);
+
+/**
+ * Loads an instance saved by `save_instance`; it is written to `instance` and
+ * has to be freed by `destroy`.
+ *
+ * # Safety
+ * `path` must be null or point to a null-terminated string. `instance` must be null
+ * or writable; what it points to is overwritten without being read.
+ */
+uint8_t load_instance(const char *path, enum Format format, struct Instance **instance);
+
+/**
+ * Appends every following change of the instance to the file at `path`.
+ *
+ * # Safety
+ * `path` must be null or point to a null-terminated string.
+ */
+uint8_t start_journal(struct Instance *instance, const char *path);
+
+void stop_journal(struct Instance *instance);
+
+/**
+ * Rebuilds an instance from the journal at `path`; it is written to `instance` and
+ * has to be freed by `destroy`.
+ *
+ * # Safety
+ * `path` must be null or point to a null-terminated string. `instance` must be null
+ * or writable; what it points to is overwritten without being read.
+ */
+uint8_t replay_journal(const char *path, struct Instance **instance);
+
+/**
+ * Like `order_product_with`, with any substitution. The id of the order is written
+ * to `order_id` even if the order gets rejected.
+ *
+ * # Safety
+ * `substitution.variant_ids` must be null or point to `substitution.variant_count` ids.
+ */
+uint8_t order_product_substituting(struct Instance *instance,
+                                   size_t id,
+                                   double amount,
+                                   size_t variant_id,
+                                   size_t user_id,
+                                   struct CSubstitution substitution,
+                                   struct OrderOptions options,
+                                   size_t *order_id);
+
+/**
+ * Weighted fair scheduling with one weight per priority level.
+ *
+ * # Safety
+ * `weights` must be null or point to `count` numbers.
+ */
+uint8_t set_weighted_fair_scheduling(struct Instance *instance,
+                                     const double *weights,
+                                     size_t count);
+
+/**
+ * Calls `callback` with `user_data` for every following notification; returns the id
+ * to unsubscribe with, nothing for a null callback. The notification is only valid
+ * during the call.
+ */
+struct COption_usize subscribe(struct Instance *instance,
+                               void (*callback)(const struct Notification*, void*),
+                               void *user_data);
+
+uint8_t unsubscribe(struct Instance *instance, size_t id);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* RESOURCE_MNG_H */
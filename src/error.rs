@@ -0,0 +1,163 @@
+/*
+* Copyright 2017-2019 Michal Mauser
+*
+* This program is free software: you can redistribute it and/or modify
+* it under the terms of the GNU Affero General Public License as published by
+* the Free Software Foundation, either version 3 of the License, or
+* (at your option) any later version.
+*
+* This program is distributed in the hope that it will be useful,
+* but WITHOUT ANY WARRANTY; without even the implied warranty of
+* MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+* GNU Affero General Public License for more details.
+*
+* You should have received a copy of the GNU Affero General Public License
+* along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::fmt;
+
+/// Reasons a call into the library can fail.
+///
+/// The C functions return the discriminant of the error, so the numbers are the same
+/// for every function. `0` means success; codes `1` to `3` are the non-zero
+/// outcomes of an accepted order (see `OrderOutcome`).
+///
+/// | code | error                 |
+/// |------|-----------------------|
+/// | 10   | `InvalidName`         |
+/// | 11   | `ZeroAmount`          |
+/// | 12   | `NoSuchMaterial`      |
+/// | 13   | `NoSuchProduct`       |
+/// | 14   | `NoSuchVariant`       |
+/// | 15   | `NoSuchOrder`         |
+/// | 16   | `DuplicateMaterial`   |
+/// | 17   | `DuplicateProduct`    |
+/// | 18   | `CircularDependency`  |
+/// | 19   | `InUse`               |
+/// | 20   | `LastVariant`         |
+/// | 21   | `Retired`             |
+/// | 22   | `NotQueued`           |
+/// | 23   | `Io`                  |
+/// | 24   | `InvalidFormat`       |
+/// | 25   | `UnsupportedVersion`  |
+/// | 26   | `InvalidReference`    |
+/// | 27   | `NoSuchSubscriber`    |
+/// | 28   | `NoSuchScarcityModel` |
+/// | 29   | `InsufficientSupply`  |
+/// | 30   | `StaleReading`        |
+/// | 31   | `PastTime`            |
+/// | 32   | `NoSuchWorkstation`   |
+/// | 33   | `InvalidPriority`     |
+/// | 255  | `Internal`            |
+#[repr(u8)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Error {
+    /// Names cannot be empty or contain only white spaces; no names, descriptions or
+    /// units can contain NUL characters.
+    InvalidName = 10,
+    /// Supplies and amounts must be finite and greater than zero; set supplies, deposits
+    /// and times may be zero.
+    ZeroAmount = 11,
+    NoSuchMaterial = 12,
+    NoSuchProduct = 13,
+    NoSuchVariant = 14,
+    NoSuchOrder = 15,
+    DuplicateMaterial = 16,
+    DuplicateProduct = 17,
+    /// The component consists, even indirectly, of the product it would be added to.
+    CircularDependency = 18,
+    /// Remove the variants using it first or remove them along with it.
+    InUse = 19,
+    /// Remove the product instead.
+    LastVariant = 20,
+    /// Retired materials, products and variants cannot be ordered.
+    Retired = 21,
+    /// The order is not waiting in the production queue anymore.
+    NotQueued = 22,
+    /// Reading or writing a saved instance failed.
+    Io = 23,
+    /// The saved instance cannot be parsed.
+    InvalidFormat = 24,
+    /// The saved instance comes from a newer version of the library.
+    UnsupportedVersion = 25,
+    /// The saved instance refers to a missing material, product, variant or order.
+    InvalidReference = 26,
+    NoSuchSubscriber = 27,
+    /// No scarcity model of the name has been added to the instance.
+    NoSuchScarcityModel = 28,
+    /// Less of the material is in supply than is to be removed.
+    InsufficientSupply = 29,
+    /// The supply reading is older than the last reading or change of the supply.
+    StaleReading = 30,
+    /// The simulation time can only move forward.
+    PastTime = 31,
+    NoSuchWorkstation = 32,
+    /// Priorities must be below the number of priority levels, of which there is at least one;
+    /// weighted fair scheduling needs a weight for every level.
+    InvalidPriority = 33,
+    /// The library panicked; the instance may be left inconsistent.
+    Internal = 255,
+}
+
+impl Error {
+    /// The number the C functions return for the error.
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Error::InvalidName => "name cannot be empty or contain only white spaces or NUL characters",
+            Error::ZeroAmount => "amount must be greater than zero",
+            Error::NoSuchMaterial => "no such material",
+            Error::NoSuchProduct => "no such product",
+            Error::NoSuchVariant => "no such product variant",
+            Error::NoSuchOrder => "no such order",
+            Error::DuplicateMaterial => "material already exists",
+            Error::DuplicateProduct => "product already exists",
+            Error::CircularDependency => "product would consist of itself",
+            Error::InUse => "still used by a product variant",
+            Error::LastVariant => "cannot remove the last variant of a product",
+            Error::Retired => "retired",
+            Error::NotQueued => "order is not queued",
+            Error::Io => "input/output error",
+            Error::InvalidFormat => "invalid file format",
+            Error::UnsupportedVersion => "unsupported file format version",
+            Error::InvalidReference => "reference to a missing item",
+            Error::NoSuchSubscriber => "no such subscriber",
+            Error::NoSuchScarcityModel => "no such scarcity model",
+            Error::InsufficientSupply => "not enough supply",
+            Error::StaleReading => "stale supply reading",
+            Error::PastTime => "time is not in the future",
+            Error::NoSuchWorkstation => "no such workstation",
+            Error::InvalidPriority => "invalid priority",
+            Error::Internal => "internal error",
+        })
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Outcome of an accepted order; the discriminant is what the C functions return.
+#[repr(u8)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum OrderOutcome {
+    /// Delivered from the finished goods inventory as a whole.
+    Delivered = 0,
+    Queued = 1,
+    /// Queued, but the materials are not available at the moment.
+    NotAvailable = 2,
+    /// Queued, but some material is scarce, whether or not it is available.
+    Scarce = 3,
+}
+
+/// Returned C code of a call.
+pub(crate) fn code<T>(result: Result<T, Error>) -> u8 {
+    match result {
+        Ok(_) => 0,
+        Err(e) => e.code(),
+    }
+}
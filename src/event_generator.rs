@@ -18,174 +18,127 @@
 use rand::Rng;
 use rand::ThreadRng;
 use resource_mng::*;
+use std::collections::BTreeMap;
 
 pub struct RunResult {
-    pub code: &'static u8,
+    pub outcome: OrderOutcome,
     pub primary_id: usize,
     pub amount: f64,
     pub secondary_id: usize,
     pub work_complexity: f64,
 }
 
-pub fn run(instance: &mut Instance, fn_num: u8, rng: &mut ThreadRng, max_values: usize) -> Result<RunResult, u8> {
+fn random_id<T>(map: &BTreeMap<usize, T>, rng: &mut ThreadRng) -> Option<usize> {
+    if map.is_empty() { return None; }
+    map.keys().nth(rng.gen::<usize>() % map.len()).cloned()
+}
+
+pub fn run(instance: &mut Instance, fn_num: u8, rng: &mut ThreadRng, max_values: usize) -> Result<RunResult, Error> {
     match fn_num {
         0 => { //add material
-            let id = rng.gen::<u16>() as usize;
+            let name = rng.gen::<u16>().to_string();
             let supply = (rng.gen::<usize>() % max_values) as f64;
-            match add_material(instance, supply) {
-                0 => {
-                    Ok(RunResult {
-                        code: &0,
-                        primary_id: id,
-                        amount: supply,
-                        secondary_id: 999_999_998, //should not be displayed
-                        work_complexity: 0.0,
-                    })
-                }
-                num => Err(num)
-            }
+            let id = instance.add_material(&name, supply)?;
+            Ok(RunResult {
+                outcome: OrderOutcome::Delivered,
+                primary_id: id,
+                amount: supply,
+                secondary_id: 999_999_998, //should not be displayed
+                work_complexity: 0.0,
+            })
         }
         1 => { // add product
-            //let id = rng.gen::<u16>() as usize;
+            let name = rng.gen::<u16>().to_string();
             let material_amount = (rng.gen::<usize>() % max_values / 32) as f64;
-            let rnd_index = rng.gen::<usize>() % get_material_count(instance);
+            let material_id = random_id(instance.get_materials(), rng).unwrap();
             let priority = rng.gen::<usize>() % 4;
             let work_complexity = rng.gen_range::<f64>(1.0, 5.0);
-            let material_id = rnd_index;
             //let work_complexity = rng.gen::<u8>();
-            match add_product(instance, material_id,
-                              material_amount, priority, work_complexity) {
-                0 => Ok(RunResult {
-                    code: &0,
-                    primary_id: instance.get_products().len(),
-                    amount: material_amount,
-                    secondary_id: material_id,
-                    work_complexity: 0.0,
-                }),
-                num => { Err(num) } //"Could not add product"
-            }
+            let id = instance.add_product(&name, material_id, material_amount, priority, work_complexity)?;
+            Ok(RunResult {
+                outcome: OrderOutcome::Delivered,
+                primary_id: id,
+                amount: material_amount,
+                secondary_id: material_id,
+                work_complexity: 0.0,
+            })
         }
-        2 | 3 | 4 | 5 => { // order product
+        2..=5 => { // order product
             let amount = (rng.gen::<usize>() % max_values / 48) as f64;
-            let product_count = get_product_count(instance);
-            let rnd_index = if product_count > 0 {
-                rng.gen::<usize>() % product_count
-            } else { return Err(6); }; //"Product database is empty."
-            let id;
-            let tmp;
-            {
-                let product_item = instance.get_products().get(rnd_index).unwrap();
-                id = rnd_index;
-                tmp = product_item.get_variant(0).components;
-            }
-            let tmp1;
-            let tmp2;
-            {//let tmp = product_item.1.get_variant(0).material_and_amount.clone();
-                tmp1 = tmp.material_id;
-                tmp2 = tmp.material_amount;
-            } //fix me
-            match order_product(instance, id, amount, 0, 0, true) {
-                1 => { //&0 not active atm
-                    //let (tmp, tmp1) = instance.get_product_types(&name).material_amount.clone_into();
-                    Ok(RunResult {
-                        code: &1,
-                        primary_id: id,
-                        amount: amount * tmp2,
-                        secondary_id: tmp1,
-                        work_complexity: 0.0,
-                    })
-                }
-                4 => {
-                    Ok(RunResult {
-                        code: &4,
-                        primary_id: id,
-                        amount,
-                        secondary_id: tmp1,
-                        work_complexity: 0.0,
-                    })
-                }
-                5 => {
-                    Ok(RunResult {
-                        code: &5,
-                        primary_id: id,
-                        amount,
-                        secondary_id: tmp1,
-                        work_complexity: 0.0,
-                    })
-                }
-                num => {
-                    Err(num)
-                }
-            }
+            let id = random_id(instance.get_products(), rng).ok_or(Error::NoSuchProduct)?; //"Product database is empty."
+            let result = instance.order_product(id, amount, 0, 0, Substitution::Any);
+            // report the scarcest material of the ordered variant
+            let component = *instance.get_product(id).unwrap().get_variant(0).unwrap().components.iter()
+                .filter(|c| c.kind == ComponentKind::Material)
+                .max_by(|a, b| {
+                    let a = instance.get_material(a.id).unwrap().get_scarcity();
+                    let b = instance.get_material(b.id).unwrap().get_scarcity();
+                    a.partial_cmp(&b).unwrap()
+                }).unwrap();
+            let (_, outcome) = result?;
+            Ok(RunResult {
+                outcome,
+                primary_id: id,
+                amount: if outcome == OrderOutcome::Queued { amount * component.amount } else { amount },
+                secondary_id: component.id,
+                work_complexity: 0.0,
+            })
         }
         6 | 7 => { // add product variant
-            let product_count = get_product_count(instance);
-            let rnd_index = if product_count > 0 {
-                rng.gen::<usize>() % product_count
-            } else { return Err(6); }; //"Product database is empty."
-            let id = rnd_index;
-
-            let material_count = get_material_count(instance);
-            let rnd_index = if material_count > 0 {
-                rng.gen::<usize>() % material_count
-            } else { return Err(6); }; //"Material database is empty."
-            let material_id = rnd_index;
+            let id = random_id(instance.get_products(), rng).ok_or(Error::NoSuchProduct)?;
+            let material_id = random_id(instance.get_materials(), rng).ok_or(Error::NoSuchMaterial)?;
 
             let material_amount = (rng.gen::<usize>() % max_values / 32) as f64;
             let work_complexity = rng.gen_range::<f64>(1.0, 5.0);
 
-            match add_product_variant(instance, id, material_id, material_amount, work_complexity) {
-                0 => Ok(RunResult {
-                    code: &0,
-                    primary_id: id,
-                    amount: 0.0,
-                    secondary_id: material_id,
-                    work_complexity,
-                }),
-                num => Err(num), //"Could not add product"
-            }
+            instance.add_product_variant(id, material_id, material_amount, work_complexity)?;
+            Ok(RunResult {
+                outcome: OrderOutcome::Delivered,
+                primary_id: id,
+                amount: 0.0,
+                secondary_id: material_id,
+                work_complexity,
+            })
         }
         8 | 9 => { // update supply
             let amount = (rng.gen::<usize>() % max_values) as f64;
-            let material_count = get_material_count(instance);
-            let rnd_index = if material_count > 0 {
-                rng.gen::<usize>() % material_count
-            } else { return Err(1); }; //"No materials in database."
-            let id = rnd_index;
-            if update_supply(instance, id, amount) {
-                Ok(RunResult {
-                    code: &0,
-                    primary_id: id,
-                    amount,
-                    secondary_id: 999_999_999, //should not be displayed
-                    work_complexity: 0.0,
-                })
-            } else { Err(2) } //"Supply update failed"
+            let id = random_id(instance.get_materials(), rng).ok_or(Error::NoSuchMaterial)?; //"No materials in database."
+            instance.update_supply(id, amount)?;
+            Ok(RunResult {
+                outcome: OrderOutcome::Delivered,
+                primary_id: id,
+                amount,
+                secondary_id: 999_999_999, //should not be displayed
+                work_complexity: 0.0,
+            })
         }
-        _ => Err(255) //"No such function"
+        _ => panic!("No such function")
     }
 }
 
 pub fn init(instance: &mut Instance, rng: &mut ThreadRng, max_values: usize, cycles: usize) {
-    add_material(instance, 10.);
+    instance.add_material("0", 10.).unwrap();
     let tmp = rng.gen::<usize>() % cycles;
     //println!("{}, {}", tmp, cycles);
     let max: usize = if cycles > 10 { tmp } else { 10 };
     let mut cnt: usize = 0;
     while cnt < max {
         if rng.gen::<u8>() % 2 == 0 {
-            //let name = rng.gen::<u16>() as usize;
+            let name = rng.gen::<u16>().to_string();
             let supply = (rng.gen::<usize>() % max_values) as f64;
-            add_material(instance, supply);
+            let _ = instance.add_material(&name, supply);
         } else {
-            //let name = rng.gen::<u16>() as usize;
+            let name = rng.gen::<u16>().to_string();
             let material_amount = (rng.gen::<usize>() % max_values / 32) as f64;
-            let rnd_index = rng.gen::<usize>() % get_material_count(instance);
+            let material_id = random_id(instance.get_materials(), rng).unwrap();
             let priority = rng.gen::<usize>() % 4;
-            let material_id = rnd_index;
             //let work_complexity = rng.gen::<u8>();
-            add_product(instance, material_id,
-                        material_amount, priority, 1.0);
+            let product = instance.add_product(&name, material_id, material_amount, priority, 1.0);
+            if let (Ok(product_id), 0) = (product, rng.gen::<u8>() % 2) {
+                let material_id = random_id(instance.get_materials(), rng).unwrap();
+                let material_amount = (rng.gen::<usize>() % max_values / 32) as f64;
+                let _ = instance.add_variant_material(product_id, 0, material_id, material_amount);
+            }
         }
         cnt += 1;
     }
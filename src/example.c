@@ -1,42 +1,70 @@
 /*
-gcc -g -O2 -Wall -c src/example.c -o main.o && gcc -o test main.o ./target/debug/libresource_mng.so && ./test
+cargo build && gcc -g -O2 -Wall -Iinclude -c src/example.c -o main.o && gcc -o test main.o -Ltarget/debug -lresource_mng && LD_LIBRARY_PATH=target/debug ./test
 */
 
 #include <stdio.h>
-#include <inttypes.h>
-#include <stdbool.h>
+#include "resource_mng.h"
 
-typedef struct Instance *box;
-
-extern uint8_t add_material (box b, double supply);
-extern uint8_t add_product (box b, size_t material_id, double material_amount, size_t priority, double work_complexity);
-extern uint8_t order_product (box b, size_t id, double amount, size_t variant_id, size_t user_id, bool allow_substitution);
-extern double get_material_supply (box b, size_t id);
-
-extern box init ();
+static void on_notification (const Notification *n, void *user_data)
+{
+    if (n->tag == Notification_ProductManufactured)
+        *(int *) user_data += 1;
+}
 
 int main ()
 {
-    size_t material_id = 0;
-    size_t product_id = material_id;
-    box b = init();
-    uint8_t result1 = add_material (b, 0.0); // 2
+    size_t material_id;
+    size_t product_id;
+    Instance *b = init();
+    int manufactured = 0;
+    subscribe (b, on_notification, &manufactured);
+    uint8_t result1 = add_material (b, "steel", 0.0, NULL); // 11 zero amount
     printf("%u\n", result1);
-    result1 = add_material (b, 100.0); // 0 ok
+    result1 = add_material (b, "steel", 100.0, &material_id); // 0 ok
     printf("%u\n", result1);
 
-    double result2 = get_material_supply (b, 0); // 100
-    printf("%f\n", result2);
+    COption_f64 result2 = get_material_supply (b, material_id); // 100
+    printf("%s: %f\n", get_material_name (b, material_id), result2.data);
 
-    result1 = add_product (b, material_id, 50.0, 0, 1.0); // 0
+    result1 = add_product (b, "chair", material_id, 50.0, 0, 1.0, &product_id); // 0
     printf("%u\n", result1);
-    result1 = add_product (b, material_id, 101.0, 0, 1.0); // 0
+    result1 = add_product (b, "table", material_id, 101.0, 0, 1.0, NULL); // 0
     printf("%u\n", result1);
-    result1 = add_product (b, 9999, 101.0, 0, 1.0); // 4
+    result1 = add_product (b, "lamp", 9999, 101.0, 0, 1.0, NULL); // 12 no such material
     printf("%u\n", result1);
 
-    result1 = order_product (b, product_id, 2.0, 0, 0, true); // 1
-    printf("%u\n", result1);
+    size_t order_id;
+    result1 = order_product (b, product_id, 2.0, 0, 0, true, &order_id); // 1 queued
+    printf("%u (order %zu)\n", result1, order_id);
+
+    process_queue (b);
+    COption_FinishedOrder finished = get_next_finished (b);
+    while (finished.is_some) {
+        printf("finished order %zu: %f x product %zu\n", finished.data.order_id,
+               finished.data.product_amount, finished.data.product_id);
+        finished = get_next_finished (b);
+    }
+    printf("steel left: %f\n", get_material_supply (b, material_id).data);
+    printf("manufactured: %d\n", manufactured);
+
+    // out of steel, the stool is made of wood instead
+    size_t wood_id, stool_id, steel_variant;
+    add_material (b, "wood", 10.0, &wood_id);
+    add_product (b, "stool", wood_id, 1.0, 0, 1.0, &stool_id);
+    add_product_variant (b, stool_id, material_id, 1.0, 1.0, &steel_variant);
+    size_t allowed[] = { 0 };
+    CSubstitution substitution = { SubstitutionKind_Only, 0.0, allowed, 1 };
+    OrderOptions options = { 0 };
+    order_product_substituting (b, stool_id, 2.0, steel_variant, 0, substitution, options, NULL);
+    printf("wood left: %f\n", get_material_supply (b, wood_id).data);
+
+    Instance *loaded;
+    if (save_instance (b, "example.bin", Format_Binary) == 0
+        && load_instance ("example.bin", Format_Binary, &loaded) == 0) {
+        printf("loaded materials: %zu\n", get_material_count (loaded));
+        destroy(loaded);
+    }
 
+    destroy(b);
     return 0;
 }
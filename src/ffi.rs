@@ -0,0 +1,273 @@
+/*
+* Copyright 2017-2019 Michal Mauser
+*
+* This program is free software: you can redistribute it and/or modify
+* it under the terms of the GNU Affero General Public License as published by
+* the Free Software Foundation, either version 3 of the License, or
+* (at your option) any later version.
+*
+* This program is distributed in the hope that it will be useful,
+* but WITHOUT ANY WARRANTY; without even the implied warranty of
+* MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+* GNU Affero General Public License for more details.
+*
+* You should have received a copy of the GNU Affero General Public License
+* along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! C entry points of functions taking or returning strings. Names are passed
+//! as null-terminated UTF-8 strings; the returned ones stay valid until the
+//! material or product is removed or changed.
+
+use crate::error::{code, Error};
+use crate::notifications::{CSubscriber, Notification};
+use crate::{COption, CSubstitution, Format, Instance, OrderOptions, Scheduling, Substitution, SubstitutionKind};
+use std::ffi::CStr;
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, BufWriter};
+use std::os::raw::{c_char, c_void};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+/// Unwinding into C is undefined behaviour, so a panic, which is a bug either way,
+/// is turned into `on_panic` at the boundary.
+pub(crate) fn guard<T>(on_panic: T, f: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(on_panic)
+}
+
+unsafe fn to_str<'a>(string: *const c_char) -> Option<&'a str> {
+    if string.is_null() { None } else { CStr::from_ptr(string).to_str().ok() }
+}
+
+impl CSubstitution {
+    unsafe fn to_substitution(self) -> Substitution {
+        match self.kind {
+            SubstitutionKind::Never => Substitution::Never,
+            SubstitutionKind::Any => Substitution::Any,
+            SubstitutionKind::WithinEfficiency => Substitution::WithinEfficiency(self.percent),
+            SubstitutionKind::Only if self.variant_ids.is_null() => Substitution::Only(Vec::new()),
+            SubstitutionKind::Only => Substitution::Only(std::slice::from_raw_parts(self.variant_ids, self.variant_count).to_vec()),
+        }
+    }
+}
+
+/// # Safety
+/// `name` must be null or point to a null-terminated string.
+#[export_name = "add_material"]
+pub unsafe extern "C" fn add_material(instance: &mut Instance, name: *const c_char, supply: f64, id: Option<&mut usize>) -> u8 {
+    guard(Error::Internal.code(), || {
+        let result = instance.add_material(to_str(name).unwrap_or(""), supply);
+        if let (Ok(material_id), Some(id)) = (&result, id) { *id = *material_id; }
+        code(result)
+    })
+}
+
+/// # Safety
+/// `name` must be null or point to a null-terminated string.
+#[export_name = "add_product"]
+pub unsafe extern "C" fn add_product(instance: &mut Instance, name: *const c_char, material_id: usize, material_amount: f64,
+                                     priority: usize, work_complexity: f64, id: Option<&mut usize>) -> u8 {
+    guard(Error::Internal.code(), || {
+        let result = instance.add_product(to_str(name).unwrap_or(""), material_id, material_amount, priority, work_complexity);
+        if let (Ok(product_id), Some(id)) = (&result, id) { *id = *product_id; }
+        code(result)
+    })
+}
+
+/// # Safety
+/// `description` must be null or point to a null-terminated string.
+#[export_name = "set_material_description"]
+pub unsafe extern "C" fn set_material_description(instance: &mut Instance, id: usize, description: *const c_char) -> u8 {
+    guard(Error::Internal.code(), || code(instance.set_material_description(id, to_str(description))))
+}
+
+/// # Safety
+/// `unit` must be null or point to a null-terminated string.
+#[export_name = "set_material_unit"]
+pub unsafe extern "C" fn set_material_unit(instance: &mut Instance, id: usize, unit: *const c_char) -> u8 {
+    guard(Error::Internal.code(), || code(instance.set_material_unit(id, to_str(unit))))
+}
+
+/// Selects the scarcity model of the given name for the material, the default one for null.
+///
+/// # Safety
+/// `name` must be null or point to a null-terminated string.
+#[export_name = "set_scarcity_model"]
+pub unsafe extern "C" fn set_scarcity_model(instance: &mut Instance, material_id: usize, name: *const c_char) -> u8 {
+    guard(Error::Internal.code(), || code(instance.set_scarcity_model(material_id, to_str(name))))
+}
+
+/// # Safety
+/// `description` must be null or point to a null-terminated string.
+#[export_name = "set_product_description"]
+pub unsafe extern "C" fn set_product_description(instance: &mut Instance, id: usize, description: *const c_char) -> u8 {
+    guard(Error::Internal.code(), || code(instance.set_product_description(id, to_str(description))))
+}
+
+/// # Safety
+/// `name` must be null or point to a null-terminated string.
+#[export_name = "find_material"]
+pub unsafe extern "C" fn find_material(instance: &Instance, name: *const c_char) -> COption<usize> {
+    guard(COption::none(), || to_str(name).and_then(|name| instance.find_material(name)).into())
+}
+
+/// # Safety
+/// `name` must be null or point to a null-terminated string.
+#[export_name = "find_product"]
+pub unsafe extern "C" fn find_product(instance: &Instance, name: *const c_char) -> COption<usize> {
+    guard(COption::none(), || to_str(name).and_then(|name| instance.find_product(name)).into())
+}
+
+/// Null if there is no such material.
+#[export_name = "get_material_name"]
+pub extern "C" fn get_material_name(instance: &Instance, id: usize) -> *const c_char {
+    instance.materials.get(&id).map_or(ptr::null(), |m| m.name.as_ptr())
+}
+
+/// Null if there is no such material or it has no description.
+#[export_name = "get_material_description"]
+pub extern "C" fn get_material_description(instance: &Instance, id: usize) -> *const c_char {
+    instance.materials.get(&id).and_then(|m| m.description.as_ref()).map_or(ptr::null(), |d| d.as_ptr())
+}
+
+/// Null if there is no such material or it has no unit.
+#[export_name = "get_material_unit"]
+pub extern "C" fn get_material_unit(instance: &Instance, id: usize) -> *const c_char {
+    instance.materials.get(&id).and_then(|m| m.unit.as_ref()).map_or(ptr::null(), |u| u.as_ptr())
+}
+
+/// Null if there is no such product.
+#[export_name = "get_product_name"]
+pub extern "C" fn get_product_name(instance: &Instance, id: usize) -> *const c_char {
+    instance.products.get(&id).map_or(ptr::null(), |p| p.name.as_ptr())
+}
+
+/// Null if there is no such product or it has no description.
+#[export_name = "get_product_description"]
+pub extern "C" fn get_product_description(instance: &Instance, id: usize) -> *const c_char {
+    instance.products.get(&id).and_then(|p| p.description.as_ref()).map_or(ptr::null(), |d| d.as_ptr())
+}
+
+/// Saves the instance to a file, replacing it if it exists.
+///
+/// # Safety
+/// `path` must be null or point to a null-terminated string.
+#[export_name = "save_instance"]
+pub unsafe extern "C" fn save_instance(instance: &Instance, path: *const c_char, format: Format) -> u8 {
+    guard(Error::Internal.code(), || {
+        let file = match to_str(path).map(File::create) {
+            Some(Ok(file)) => file,
+            _ => return Error::Io.code(),
+        };
+        code(instance.save(BufWriter::new(file), format))
+    })
+}
+
+/// Loads an instance saved by `save_instance`; it is written to `instance` and
+/// has to be freed by `destroy`.
+///
+/// # Safety
+/// `path` must be null or point to a null-terminated string. `instance` must be null
+/// or writable; what it points to is overwritten without being read.
+#[export_name = "load_instance"]
+pub unsafe extern "C" fn load_instance(path: *const c_char, format: Format, instance: *mut *mut Instance) -> u8 {
+    guard(Error::Internal.code(), || {
+        let file = match to_str(path).map(File::open) {
+            Some(Ok(file)) => file,
+            _ => return Error::Io.code(),
+        };
+        match Instance::load(BufReader::new(file), format) {
+            Ok(loaded) => {
+                if !instance.is_null() { ptr::write(instance, Box::into_raw(Box::new(loaded))); }
+                0
+            }
+            Err(e) => e.code(),
+        }
+    })
+}
+
+/// Appends every following change of the instance to the file at `path`.
+///
+/// # Safety
+/// `path` must be null or point to a null-terminated string.
+#[export_name = "start_journal"]
+pub unsafe extern "C" fn start_journal(instance: &mut Instance, path: *const c_char) -> u8 {
+    guard(Error::Internal.code(), || {
+        match to_str(path).map(|path| OpenOptions::new().create(true).append(true).open(path)) {
+            Some(Ok(file)) => instance.start_journal(file),
+            _ => return Error::Io.code(),
+        }
+        0
+    })
+}
+
+#[export_name = "stop_journal"]
+pub extern "C" fn stop_journal(instance: &mut Instance) {
+    guard((), || instance.stop_journal());
+}
+
+/// Rebuilds an instance from the journal at `path`; it is written to `instance` and
+/// has to be freed by `destroy`.
+///
+/// # Safety
+/// `path` must be null or point to a null-terminated string. `instance` must be null
+/// or writable; what it points to is overwritten without being read.
+#[export_name = "replay_journal"]
+pub unsafe extern "C" fn replay_journal(path: *const c_char, instance: *mut *mut Instance) -> u8 {
+    guard(Error::Internal.code(), || {
+        let file = match to_str(path).map(File::open) {
+            Some(Ok(file)) => file,
+            _ => return Error::Io.code(),
+        };
+        match Instance::replay(BufReader::new(file)) {
+            Ok(replayed) => {
+                if !instance.is_null() { ptr::write(instance, Box::into_raw(Box::new(replayed))); }
+                0
+            }
+            Err(e) => e.code(),
+        }
+    })
+}
+
+/// Like `order_product_with`, with any substitution. The id of the order is written
+/// to `order_id` even if the order gets rejected.
+///
+/// # Safety
+/// `substitution.variant_ids` must be null or point to `substitution.variant_count` ids.
+#[export_name = "order_product_substituting"]
+pub unsafe extern "C" fn order_product_substituting(instance: &mut Instance, id: usize, amount: f64, variant_id: usize, user_id: usize,
+                                                    substitution: CSubstitution, options: OrderOptions,
+                                                    order_id: Option<&mut usize>) -> u8 {
+    guard(Error::Internal.code(), || {
+        if let Some(order_id) = order_id { *order_id = instance.next_order_id; }
+        match instance.order_product_with(id, amount, variant_id, user_id, substitution.to_substitution(), options) {
+            Ok((_, outcome)) => outcome as u8,
+            Err(e) => e.code(),
+        }
+    })
+}
+
+/// Weighted fair scheduling with one weight per priority level.
+///
+/// # Safety
+/// `weights` must be null or point to `count` numbers.
+#[export_name = "set_weighted_fair_scheduling"]
+pub unsafe extern "C" fn set_weighted_fair_scheduling(instance: &mut Instance, weights: *const f64, count: usize) -> u8 {
+    guard(Error::Internal.code(), || {
+        let weights = if weights.is_null() { Vec::new() } else { std::slice::from_raw_parts(weights, count).to_vec() };
+        code(instance.set_scheduling(Scheduling::WeightedFair(weights)))
+    })
+}
+
+/// Calls `callback` with `user_data` for every following notification; returns the id
+/// to unsubscribe with, nothing for a null callback. The notification is only valid
+/// during the call.
+#[export_name = "subscribe"]
+pub extern "C" fn subscribe(instance: &mut Instance, callback: Option<extern "C" fn(&Notification, *mut c_void)>, user_data: *mut c_void) -> COption<usize> {
+    guard(COption::none(), || callback.map(|callback| instance.subscribe(CSubscriber { callback, user_data })).into())
+}
+
+#[export_name = "unsubscribe"]
+pub extern "C" fn unsubscribe(instance: &mut Instance, id: usize) -> u8 {
+    guard(Error::Internal.code(), || code(instance.unsubscribe(id)))
+}
@@ -15,14 +15,41 @@
 * along with this program.  If not, see <http://www.gnu.org/licenses/>.
 */
 
-use crate::{Order, Product, ProductVariant, Material, PRIORITIES};
+use crate::journal::Journal;
+use crate::notifications::Subscribers;
+use crate::reservations::Reservations;
+use crate::{ComponentKind, Event, Instance, Materials, MissedDeadline, Notification, Order, OrderOptions, OrderState,
+            Production, Products, ProductVariant, Substitution, Workstations};
 use std::cmp::Ordering;
+use std::collections::HashMap;
 
 impl ProductVariant {
+    // the scarcest component limits the whole variant
+    fn get_scarcity(&self) -> f64 {
+        self.components.iter().fold(0.0, |max, c| c.scarcity_cache.max(max))
+    }
+
     fn get_production_efficiency(&self) -> f64 {
-        if self.components.scarcity_cache == std::f64::INFINITY {
-            std::f64::MAX
-        } else { self.components.scarcity_cache / self.work_complexity }
+        let scarcity = self.get_scarcity();
+        // effortless variants are as good as their materials are plentiful
+        if scarcity == 0.0 {
+            0.0
+        } else if scarcity == f64::INFINITY || self.work_complexity == 0.0 {
+            f64::MAX
+        } else { scarcity / self.work_complexity }
+    }
+}
+
+impl Substitution {
+    fn allows(&self, preferred: &ProductVariant, variant: &ProductVariant) -> bool {
+        if preferred.id == variant.id { return true; }
+        match self {
+            Substitution::Never => false,
+            Substitution::Any => true,
+            Substitution::WithinEfficiency(percent) => variant.get_production_efficiency()
+                <= preferred.get_production_efficiency() * (1.0 + percent / 100.0),
+            Substitution::Only(ids) => ids.contains(&variant.id),
+        }
     }
 }
 
@@ -42,67 +69,394 @@ impl PartialEq for ProductVariant {
     }
 }
 
-pub fn process_queue(production_queue: &mut [Vec<Order>; PRIORITIES],
-                     products: &mut Vec<Product>,
-                     materials: &mut Vec<Material>,
-                     finished_products: &mut Vec<Order>,
-                     verbose: usize)
-{
-    for q in production_queue.iter_mut() {
-        let mut i: usize = 0;
-        //let mut to_remove = Vec::new();
-        while i != q.len() {
-            let mut found = false;
-            let q_product = products.get_mut(q[i].product_id).unwrap();
-
-            // update scarcity cache for components; better solution wanted
-            for mut variant in q_product.variants.iter_mut() {
-                let mut variant_material = materials.get_mut(variant.components.material_id).unwrap();
-                variant_material.scarcity_cache = variant_material.get_scarcity();
-                variant.components.scarcity_cache = variant_material.scarcity_cache;
+/// Whether `product_id` is `target` or is (even indirectly) made of it.
+pub fn depends_on(products: &Products, product_id: usize, target: usize) -> bool {
+    product_id == target || products[&product_id].variants.iter()
+        .flat_map(|v| v.components.iter())
+        .any(|c| c.kind == ComponentKind::Product && depends_on(products, c.id, target))
+}
+
+/// Scarcity of a product is that of its least scarce variant.
+pub fn get_product_scarcity(products: &Products, materials: &Materials, product_id: usize) -> f64 {
+    products[&product_id].variants.iter()
+        .map(|v| v.components.iter().fold(0.0, |max: f64, c| {
+            let scarcity = match c.kind {
+                ComponentKind::Material => materials[&c.id].get_scarcity(),
+                ComponentKind::Product => get_product_scarcity(products, materials, c.id),
+            };
+            scarcity.max(max)
+        }))
+        .fold(f64::INFINITY, f64::min)
+}
+
+/// Whether the variant can be made right now, sub-products either from stock
+/// or from their own components.
+pub fn can_supply(products: &Products, materials: &Materials, variant: &ProductVariant, amount: f64) -> bool {
+    variant.components.iter().all(|c| {
+        let needed = c.amount * amount;
+        match c.kind {
+            ComponentKind::Material => materials[&c.id].supply >= needed,
+            ComponentKind::Product => {
+                let shortfall = needed - products[&c.id].supply;
+                shortfall <= 0.0 || products[&c.id].variants.iter()
+                    .any(|v| can_supply(products, materials, v, shortfall))
             }
+        }
+    })
+}
 
-            // sort variants by efficiency except the preferred one
-            if q_product.variants.len() > 1 {
-                if verbose >= crate::VERBOSITY_INNER {
-                    if cfg!(feature = "cz") {
-                        println!(" * Kalkuluji nejefektivnější variantu produktu #{} pro výrobu.",
-                                 q[i].product_id);
-                    } else {
-                        println!(" * Calculating the most efficient variant of product #{} for production.",
-                                 q[i].product_id);
-                    }
+// update scarcity cache for components; better solution wanted
+pub fn refresh_scarcity(products: &mut Products, materials: &mut Materials, product_id: usize) {
+    let mut caches = Vec::new();
+    for variant in products[&product_id].variants.iter() {
+        for component in variant.components.iter() {
+            caches.push(match component.kind {
+                ComponentKind::Material => {
+                    let variant_material = materials.get_mut(&component.id).unwrap();
+                    variant_material.scarcity_cache = variant_material.get_scarcity();
+                    variant_material.scarcity_cache
                 }
-                let index = q_product.variants.iter().position(|x| x.id == q[i].preferred_variant).unwrap();
-                let swap = q_product.variants.remove(index);
-                if q_product.variants.len() > 1 { q_product.variants.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap()); }
-                q_product.variants.insert(0, swap);
+                ComponentKind::Product => get_product_scarcity(products, materials, component.id),
+            });
+        }
+    }
+    let components = products.get_mut(&product_id).unwrap().variants.iter_mut().flat_map(|v| v.components.iter_mut());
+    for (component, scarcity) in components.zip(caches) {
+        component.scarcity_cache = scarcity;
+    }
+}
+
+// the variant a sub-order prefers: the most efficient one still made that can be made now,
+// else the most efficient one still made
+fn pick_variant<'a>(products: &'a Products, materials: &Materials, product_id: usize, amount: f64) -> &'a ProductVariant {
+    let mut variants: Vec<_> = products[&product_id].variants.iter().filter(|v| !v.retired).collect();
+    if variants.is_empty() { return &products[&product_id].variants[0]; }
+    variants.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    variants.iter().find(|v| can_supply(products, materials, v, amount)).unwrap_or(&variants[0])
+}
+
+// queues production of sub-products missing for the order at `index` right before it;
+// returns the number of queued sub-orders
+fn schedule_components(q: &mut Vec<Order>, index: usize, products: &mut Products, materials: &mut Materials,
+                       in_production: &[Production], next_order_id: &mut usize, time: f64) -> usize {
+    let variant = products[&q[index].product_id].get_variant(q[index].preferred_variant).unwrap().clone();
+    let mut scheduled = 0;
+    for component in variant.components.iter().filter(|c| c.kind == ComponentKind::Product) {
+        let needed = component.amount * q[index].product_amount;
+        // sub-products already queued or being made to stock arrive in time
+        let pending: f64 = q.iter().chain(in_production.iter().map(|p| &p.order))
+            .filter(|o| o.for_stock && o.product_id == component.id)
+            .map(|o| o.product_amount)
+            .sum();
+        let shortfall = needed - products[&component.id].supply - pending;
+        if shortfall <= 0.0 { continue; }
+
+        let sub_variant = pick_variant(products, materials, component.id, shortfall).clone();
+        sub_variant.add_demand(products, materials, shortfall);
+        // variants listed by the order are its product's, other substitutions carry over
+        let substitution = match q[index].substitution {
+            Substitution::Never => Substitution::Never,
+            Substitution::WithinEfficiency(percent) => Substitution::WithinEfficiency(percent),
+            _ => Substitution::Any,
+        };
+        let sub_order = Order {
+            id: *next_order_id,
+            product_id: component.id,
+            product_amount: shortfall,
+            preferred_variant: sub_variant.id,
+            user_id: q[index].user_id,
+            substitution,
+            for_stock: true,
+            options: OrderOptions::default(),
+            queued_at: time,
+            parent: Some(q[index].id),
+        };
+        *next_order_id += 1;
+        q.insert(index, sub_order);
+        scheduled += 1;
+    }
+    scheduled
+}
+
+/// Variants of all products consisting of the given material or product.
+pub fn get_variants_using(products: &Products, kind: ComponentKind, id: usize) -> Vec<(usize, usize)> {
+    let mut users = Vec::new();
+    for (product_id, product) in products.iter() {
+        for variant in product.variants.iter() {
+            if variant.components.iter().any(|c| c.kind == kind && c.id == id) {
+                users.push((*product_id, variant.id));
             }
+        }
+    }
+    users
+}
+
+/// Removes the variant rerouting or cancelling the orders preferring it;
+/// a product left without variants is removed too.
+pub fn remove_variant(instance: &mut Instance, product_id: usize, variant_id: usize) {
+    // the product may have been removed by an earlier cascade
+    let product = match instance.products.get(&product_id) {
+        Some(p) => p,
+        None => return,
+    };
+    if product.variants.len() == 1 {
+        remove_product(instance, product_id);
+        return;
+    }
 
-            // manufacture the first one to meet conditions
-            for variant in q_product.variants.clone() {
-                let q_material = materials.get_mut(variant.components.material_id).unwrap();
-                let material_amount = q[i].product_amount * variant.components.material_amount;
-                if q_material.supply >= material_amount {
-                    if variant.id != q[i].preferred_variant { q_material.demand += material_amount; }
-                    q_product.manufacture(q_material, q[i].product_amount, &variant);
-                    q_product.deliver(q[i].product_amount);
-                    let finished_product = q.remove(i);
-                    if verbose >= crate::VERBOSITY_INNER {
-                        if cfg!(feature = "cz") {
-                            println!(" * Vyrábím {}x produkt #{}, varianta #{} (preferovaná byla {}) z fronty priority {}.",
-                                     finished_product.product_amount, finished_product.product_id, variant.id, finished_product.preferred_variant, q_product.priority + 1);
-                        } else {
-                            println!(" * Manufacturing {}x product #{}, variant #{} (preferred was {}) from priority {} production queue.",
-                                     finished_product.product_amount, finished_product.product_id, variant.id, finished_product.preferred_variant, q_product.priority + 1);
-                        }
-                    }
-                    finished_products.push(finished_product);
-                    found = true;
-                    break;
+    let Instance { production_queue, products, materials, reservations, .. } = instance;
+    // efficiencies are compared as when the order is manufactured
+    refresh_scarcity(products, materials, product_id);
+    let removed = products[&product_id].get_variant(variant_id).unwrap().clone();
+    let mut cancelled = Vec::new();
+    for (priority, q) in production_queue.iter_mut().enumerate() {
+        for order in q.iter_mut().filter(|o| o.product_id == product_id && o.preferred_variant == variant_id) {
+            let replacement = products[&product_id].variants.iter()
+                .find(|v| v.id != variant_id && order.substitution.allows(&removed, v))
+                .cloned();
+            match replacement {
+                Some(variant) => {
+                    removed.add_demand(products, materials, -order.product_amount);
+                    variant.add_demand(products, materials, order.product_amount);
+                    order.preferred_variant = variant.id;
+                    // what was set aside for the removed variant goes to the replacement
+                    reservations.release(materials, order.id);
+                    reservations.reserve(materials, order.id, priority, &variant, order.product_amount);
                 }
+                None => cancelled.push(order.id),
+            }
+        }
+    }
+    for id in cancelled {
+        let _ = instance.cancel_order(id);
+    }
+
+    let product = instance.products.get_mut(&product_id).unwrap();
+    product.variants.retain(|v| v.id != variant_id);
+}
+
+/// Removes the product cancelling its orders and removing all variants made of it.
+pub fn remove_product(instance: &mut Instance, product_id: usize) {
+    let orders: Vec<usize> = instance.production_queue.iter().flatten()
+        .filter(|o| o.product_id == product_id)
+        .map(|o| o.id)
+        .collect();
+    for id in orders {
+        let _ = instance.cancel_order(id);
+    }
+    for (user_id, variant_id) in get_variants_using(&instance.products, ComponentKind::Product, product_id) {
+        remove_variant(instance, user_id, variant_id);
+    }
+    instance.products.remove(&product_id);
+}
+
+pub fn process_queue(instance: &mut Instance) {
+    let Instance {
+        production_queue, products, materials, finished_products, in_production,
+        order_states, next_order_id, journal, subscribers, time, time_per_complexity, workstations, reservations, scheduling, ..
+    } = instance;
+    // levels take turns as the scheduling decides, each is gone through once
+    let mut cursors = vec![0; production_queue.len()];
+    let mut visits = vec![0; production_queue.len()];
+    while let Some(priority) = scheduling.next_level(production_queue, &cursors, &visits, *time) {
+        visits[priority] += 1;
+        let q = &mut production_queue[priority];
+        let i = cursors[priority];
+        refresh_scarcity(products, materials, q[i].product_id);
+        let q_product = products.get_mut(&q[i].product_id).unwrap();
+
+        // sort variants by efficiency except the preferred one
+        let preferred = q[i].preferred_variant;
+        q_product.variants.sort_by(|a, b| {
+            (b.id == preferred).cmp(&(a.id == preferred)).then_with(|| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+        });
+        let variants = q_product.variants.clone();
+        // supply that has come in since is reserved in the order of processing
+        reservations.reserve(materials, q[i].id, priority, &variants[0], q[i].product_amount);
+
+        // manufacture the first one to meet conditions, or parts of the order if it allows
+        let amount = q[i].product_amount;
+        let whole = variants.iter().position(|variant| {
+            q[i].substitution.allows(&variants[0], variant)
+                && variant.workstation.is_none_or(|id| workstations[&id].fits(variant.work_complexity * amount))
+                && variant.is_available(products, materials, reservations, q[i].id, priority, amount)
+        });
+        let parts = match whole {
+            Some(index) => vec![(index, amount)],
+            None => plan_parts(&q[i], priority, &variants, products, materials, workstations, reservations),
+        };
+        let mut remainder = amount;
+        for (index, part) in parts {
+            let variant = &variants[index];
+            if let Some(workstation) = variant.workstation.and_then(|id| workstations.get_mut(&id)) {
+                workstation.used += variant.work_complexity * part;
+                workstation.total_used += variant.work_complexity * part;
+            }
+            subscribers.notify(Notification::VariantSelected {
+                order_id: q[i].id, product_id: q[i].product_id, variant_id: variant.id, preferred_variant: q[i].preferred_variant,
+            });
+            if variant.id != q[i].preferred_variant {
+                // move the demand from the preferred variant to the substitute
+                variants[0].add_demand(products, materials, -part);
+                variant.add_demand(products, materials, part);
+            }
+            reservations.consume(materials, q[i].id, priority, variant, part);
+            variant.consume(products, materials, part, *time);
+            remainder -= part;
+            let order = Order { product_amount: part, ..q[i].clone() };
+            let duration = variant.duration(*time_per_complexity);
+            if duration > 0.0 {
+                in_production.push(Production { order, variant_id: variant.id, completes_at: *time + duration });
+            } else {
+                finish(&order, variant.id, products, finished_products, journal, subscribers);
+            }
+        }
+        if remainder < amount {
+            let order = if remainder > 0.0 {
+                q[i].product_amount = remainder;
+                q[i].clone()
+            } else {
+                reservations.release(materials, q[i].id);
+                q.remove(i)
+            };
+            if remainder > 0.0 {
+                order_states.insert(order.id, OrderState::Queued);
+            } else if in_production.iter().any(|p| p.order.id == order.id) {
+                order_states.insert(order.id, OrderState::InProduction);
+            } else {
+                mark_finished(&Order { product_amount: amount, ..order }, order_states, subscribers);
+            }
+            if remainder <= 0.0 { continue; }
+        }
+        // sub-products scheduled in front of the order get processed first
+        let scheduled = schedule_components(q, i, products, materials, in_production, next_order_id, *time);
+        if scheduled == 0 {
+            cursors[priority] += 1;
+        } else {
+            order_states.insert(q[i + scheduled].id, OrderState::InProduction);
+            for sub_order in q[i..i + scheduled].iter() {
+                order_states.insert(sub_order.id, OrderState::Queued);
+                subscribers.notify(Notification::OrderQueued {
+                    order_id: sub_order.id, product_id: sub_order.product_id, amount: sub_order.product_amount,
+                    priority, for_stock: true,
+                });
+            }
+        }
+    }
+    notify_materials(materials, subscribers);
+}
+
+// the parts an order that no single variant can cover can be made of now,
+// preferred variant first; empty unless the order allows them
+fn plan_parts(order: &Order, priority: usize, variants: &[ProductVariant], products: &Products, materials: &Materials,
+              workstations: &Workstations, reservations: &Reservations) -> Vec<(usize, f64)> {
+    if !order.options.partial && !order.options.split { return Vec::new(); }
+    let mut products = products.clone();
+    let mut materials = materials.clone();
+    let mut workstations = workstations.clone();
+    let mut reservations = reservations.clone();
+    let mut parts = Vec::new();
+    let mut remainder = order.product_amount;
+    for (index, variant) in variants.iter().enumerate() {
+        if !order.substitution.allows(&variants[0], variant) { continue; }
+        let mut part = variant.components.iter().map(|c| match c.kind {
+            ComponentKind::Material => reservations.available(&materials, order.id, priority, c.id) / c.amount,
+            ComponentKind::Product => products[&c.id].supply / c.amount,
+        }).fold(remainder, f64::min);
+        if let Some(workstation) = variant.workstation.and_then(|id| workstations.get_mut(&id)) {
+            if variant.work_complexity > 0.0 {
+                part = part.min((workstation.capacity - workstation.used).max(0.0) / variant.work_complexity);
+            }
+            workstation.used += variant.work_complexity * part;
+        }
+        if part <= 0.0 { continue; }
+        reservations.consume(&mut materials, order.id, priority, variant, part);
+        variant.consume(&mut products, &mut materials, part, 0.0);
+        parts.push((index, part));
+        remainder -= part;
+        if remainder <= 0.0 || !order.options.split { break; }
+    }
+    if remainder > 0.0 && !order.options.partial { parts.clear(); }
+    parts
+}
+
+fn mark_finished(order: &Order, order_states: &mut HashMap<usize, OrderState>, subscribers: &mut Subscribers) {
+    order_states.insert(order.id, OrderState::Finished);
+    subscribers.notify(Notification::OrderFinished {
+        order_id: order.id, product_id: order.product_id, amount: order.product_amount, user_id: order.user_id,
+    });
+}
+
+// manufactures an order or a part of it and hands it over unless made to stock
+fn finish(order: &Order, variant_id: usize, products: &mut Products, finished_products: &mut Vec<Order>,
+          journal: &mut Journal, subscribers: &mut Subscribers) {
+    let amount = order.product_amount;
+    let product = products.get_mut(&order.product_id).unwrap();
+    product.manufacture(amount);
+    journal.record(Event::Manufacture { order_id: order.id, product_id: order.product_id, variant_id, amount });
+    subscribers.notify(Notification::ProductManufactured { order_id: order.id, product_id: order.product_id, variant_id, amount });
+    if !order.for_stock {
+        product.deliver(amount);
+        journal.record(Event::Deliver { order_id: order.id, product_id: order.product_id, amount });
+        finished_products.push(order.clone());
+    }
+}
+
+/// Finishes the orders in production due by now, in the order of their completion.
+pub fn complete_production(instance: &mut Instance) {
+    let Instance {
+        production_queue, in_production, products, order_states, finished_products, journal, subscribers, time, ..
+    } = instance;
+    let (mut due, pending): (Vec<Production>, Vec<Production>) = in_production.drain(..).partition(|p| p.completes_at <= *time);
+    *in_production = pending;
+    due.sort_by(|a, b| a.completes_at.partial_cmp(&b.completes_at).unwrap());
+    for production in due {
+        // the product may have been removed in the meantime
+        let order = &production.order;
+        if products.contains_key(&order.product_id) {
+            finish(order, production.variant_id, products, finished_products, journal, subscribers);
+            let pending = in_production.iter().any(|p| p.order.id == order.id)
+                || production_queue.iter().flatten().any(|o| o.id == order.id);
+            if !pending && order_states.get(&order.id) == Some(&OrderState::InProduction) {
+                mark_finished(order, order_states, subscribers);
             }
-            if !found { i += 1; }
+        } else {
+            order_states.insert(production.order.id, OrderState::Cancelled);
+        }
+    }
+}
+
+/// Records queued orders and orders being manufactured whose due date has passed and
+/// tells the subscribers about them, once per order.
+pub fn check_deadlines(instance: &mut Instance) {
+    let Instance { production_queue, in_production, missed_deadlines, subscribers, time, .. } = instance;
+    let orders = production_queue.iter().flatten().chain(in_production.iter().map(|p| &p.order));
+    for order in orders {
+        let due = match order.due() {
+            Some(due) if due < *time => due,
+            _ => continue,
+        };
+        if missed_deadlines.iter().any(|m| m.order_id == order.id) { continue; }
+        missed_deadlines.push(MissedDeadline { order_id: order.id, product_id: order.product_id, user_id: order.user_id, due });
+        subscribers.notify(Notification::DeadlineMissed { order_id: order.id, product_id: order.product_id, due });
+    }
+}
+
+/// Tells the subscribers about materials that have become scarce or started running out
+/// since the last time.
+pub fn notify_materials(materials: &mut Materials, subscribers: &mut Subscribers) {
+    for (id, material) in materials.iter_mut() {
+        let scarcity = material.get_scarcity();
+        let scarce = material.is_scarce();
+        if scarce && !material.scarce {
+            subscribers.notify(Notification::MaterialScarce { material_id: *id, scarcity });
+        }
+        material.scarce = scarce;
+
+        let depleting = material.is_depleting();
+        if depleting && !material.depleting {
+            subscribers.notify(Notification::MaterialDepleting { material_id: *id, remaining: material.remaining() });
         }
+        material.depleting = depleting;
     }
-}
\ No newline at end of file
+}
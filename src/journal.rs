@@ -0,0 +1,205 @@
+/*
+* Copyright 2017-2019 Michal Mauser
+*
+* This program is free software: you can redistribute it and/or modify
+* it under the terms of the GNU Affero General Public License as published by
+* the Free Software Foundation, either version 3 of the License, or
+* (at your option) any later version.
+*
+* This program is distributed in the hope that it will be useful,
+* but WITHOUT ANY WARRANTY; without even the implied warranty of
+* MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+* GNU Affero General Public License for more details.
+*
+* You should have received a copy of the GNU Affero General Public License
+* along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Append-only journal of the changes made to an instance.
+//!
+//! Every event is written as one line of JSON as soon as it happens. Calls changing
+//! the instance are recorded as they were made; manufacture and deliver steps are
+//! recorded for the audit trail only, replaying the calls repeats them.
+
+use crate::{Error, Instance, OrderOptions, OrderState, Preemption, Regeneration, Scheduling, Substitution};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, Write};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Event {
+    AddMaterial { id: usize, name: String, supply: f64 },
+    AddProduct { id: usize, name: String, material_id: usize, material_amount: f64, priority: usize, work_complexity: f64 },
+    AddProductVariant { product_id: usize, id: usize, material_id: usize, material_amount: f64, work_complexity: f64 },
+    AddVariantMaterial { product_id: usize, variant_id: usize, material_id: usize, amount: f64 },
+    AddVariantProduct { product_id: usize, variant_id: usize, component_id: usize, amount: f64 },
+    /// Recorded for rejected orders as well, they take up an order id.
+    OrderProduct {
+        order_id: usize, product_id: usize, amount: f64, variant_id: usize, user_id: usize, substitution: Substitution,
+        #[serde(default)]
+        options: OrderOptions,
+    },
+    ProduceToStock { order_id: usize, product_id: usize, amount: f64, variant_id: usize, substitution: Substitution },
+    /// An order rejected for its amount, substitution or due date before it was recorded;
+    /// it takes up an order id all the same.
+    RejectOrder { order_id: usize },
+    CancelOrder { order_id: usize },
+    ProcessQueue,
+    UpdateSupply { material_id: usize, amount: f64 },
+    RemoveMaterial { id: usize, cascade: bool },
+    RemoveProduct { id: usize, cascade: bool },
+    RemoveProductVariant { product_id: usize, variant_id: usize },
+    RetireMaterial { id: usize },
+    RetireProduct { id: usize },
+    RetireProductVariant { product_id: usize, variant_id: usize },
+    SetMaterialDescription { id: usize, description: Option<String> },
+    SetMaterialUnit { id: usize, unit: Option<String> },
+    SetProductDescription { id: usize, description: Option<String> },
+    SetScarcityModel { material_id: usize, name: Option<String> },
+    UpdateDepositSize { material_id: usize, size: f64 },
+    Extract { material_id: usize, amount: f64 },
+    SetRegeneration { material_id: usize, regeneration: Option<Regeneration> },
+    AdvanceTime { duration: f64 },
+    AdvanceTo { time: f64 },
+    SetTimePerComplexity { time: f64 },
+    SetVariantDuration { product_id: usize, variant_id: usize, duration: Option<f64> },
+    AddWorkstation { id: usize, capacity: f64 },
+    SetPreemption { preemption: Preemption },
+    SetPriorityLevels { levels: usize },
+    SetScheduling { scheduling: Scheduling },
+    SetWorkstationCapacity { id: usize, capacity: f64 },
+    AssignWorkstation { product_id: usize, variant_id: usize, workstation_id: Option<usize> },
+    AddSupply { material_id: usize, amount: f64 },
+    RemoveSupply { material_id: usize, amount: f64 },
+    ReadSupply { material_id: usize, amount: f64, timestamp: f64 },
+    /// A finished order was handed over to the user.
+    TakeFinished,
+    Reset,
+    Manufacture { order_id: usize, product_id: usize, variant_id: usize, amount: f64 },
+    Deliver { order_id: usize, product_id: usize, amount: f64 },
+}
+
+/// Where the events go; copies of an instance are not journaled.
+#[derive(Default)]
+pub(crate) struct Journal {
+    writer: Option<Box<dyn Write + Send>>,
+}
+
+impl Clone for Journal {
+    fn clone(&self) -> Self {
+        Journal::default()
+    }
+}
+
+impl Journal {
+    pub(crate) fn new<W: Write + Send + 'static>(writer: W) -> Journal {
+        Journal { writer: Some(Box::new(writer)) }
+    }
+
+    pub(crate) fn is_active(&self) -> bool {
+        self.writer.is_some()
+    }
+
+    /// A journal that cannot be written to is closed, so that it is not continued with gaps.
+    pub(crate) fn record(&mut self, event: Event) {
+        if let Some(writer) = self.writer.as_mut() {
+            let written = serde_json::to_writer(&mut *writer, &event).is_ok()
+                && writer.write_all(b"\n").is_ok()
+                && writer.flush().is_ok();
+            if !written { self.writer = None; }
+        }
+    }
+}
+
+pub(crate) fn replay<R: BufRead>(mut instance: Instance, reader: R) -> Result<Instance, Error> {
+    for line in reader.lines() {
+        let line = line.map_err(|_| Error::Io)?;
+        if line.trim().is_empty() { continue; }
+        let event: Event = serde_json::from_str(&line).map_err(|_| Error::InvalidFormat)?;
+        apply(&mut instance, event)?;
+    }
+    Ok(instance)
+}
+
+fn apply(instance: &mut Instance, event: Event) -> Result<(), Error> {
+    // the ids have to come out the same, otherwise the journal does not belong to an empty instance
+    fn same(recorded: usize, replayed: usize) -> Result<(), Error> {
+        if recorded == replayed { Ok(()) } else { Err(Error::InvalidReference) }
+    }
+
+    match event {
+        Event::AddMaterial { id, name, supply } => same(id, instance.add_material(&name, supply)?),
+        Event::AddProduct { id, name, material_id, material_amount, priority, work_complexity } =>
+            same(id, instance.add_product(&name, material_id, material_amount, priority, work_complexity)?),
+        Event::AddProductVariant { product_id, id, material_id, material_amount, work_complexity } =>
+            same(id, instance.add_product_variant(product_id, material_id, material_amount, work_complexity)?),
+        Event::AddVariantMaterial { product_id, variant_id, material_id, amount } =>
+            instance.add_variant_material(product_id, variant_id, material_id, amount),
+        Event::AddVariantProduct { product_id, variant_id, component_id, amount } =>
+            instance.add_variant_product(product_id, variant_id, component_id, amount),
+        Event::OrderProduct { order_id, product_id, amount, variant_id, user_id, substitution, options } => {
+            same(order_id, instance.next_order_id)?;
+            let _ = instance.order_product_with(product_id, amount, variant_id, user_id, substitution, options);
+            Ok(())
+        }
+        Event::ProduceToStock { order_id, product_id, amount, variant_id, substitution } => {
+            same(order_id, instance.next_order_id)?;
+            let _ = instance.produce_to_stock(product_id, amount, variant_id, substitution);
+            Ok(())
+        }
+        Event::RejectOrder { order_id } => {
+            same(order_id, instance.next_order_id)?;
+            let id = instance.new_order_id();
+            instance.order_states.insert(id, OrderState::Rejected);
+            instance.journal.record(Event::RejectOrder { order_id: id });
+            Ok(())
+        }
+        Event::CancelOrder { order_id } => instance.cancel_order(order_id),
+        Event::ProcessQueue => {
+            instance.process_queue();
+            Ok(())
+        }
+        Event::UpdateSupply { material_id, amount } => {
+            let _ = instance.update_supply(material_id, amount);
+            Ok(())
+        }
+        Event::RemoveMaterial { id, cascade } => instance.remove_material(id, cascade),
+        Event::RemoveProduct { id, cascade } => instance.remove_product(id, cascade),
+        Event::RemoveProductVariant { product_id, variant_id } => instance.remove_product_variant(product_id, variant_id),
+        Event::RetireMaterial { id } => instance.retire_material(id),
+        Event::RetireProduct { id } => instance.retire_product(id),
+        Event::RetireProductVariant { product_id, variant_id } => instance.retire_product_variant(product_id, variant_id),
+        Event::SetMaterialDescription { id, description } => instance.set_material_description(id, description.as_deref()),
+        Event::SetMaterialUnit { id, unit } => instance.set_material_unit(id, unit.as_deref()),
+        Event::SetProductDescription { id, description } => instance.set_product_description(id, description.as_deref()),
+        Event::SetScarcityModel { material_id, name } => instance.set_scarcity_model(material_id, name.as_deref()),
+        Event::UpdateDepositSize { material_id, size } => instance.update_deposit_size(material_id, size),
+        Event::Extract { material_id, amount } => instance.extract(material_id, amount).map(|_| ()),
+        Event::SetRegeneration { material_id, regeneration } => instance.set_regeneration(material_id, regeneration),
+        Event::AdvanceTime { duration } => instance.advance_time(duration),
+        Event::AdvanceTo { time } => instance.advance_to(time),
+        Event::SetTimePerComplexity { time } => instance.set_time_per_complexity(time),
+        Event::SetVariantDuration { product_id, variant_id, duration } => instance.set_variant_duration(product_id, variant_id, duration),
+        Event::AddWorkstation { id, capacity } => same(id, instance.add_workstation(capacity)?),
+        Event::SetPriorityLevels { levels } => instance.set_priority_levels(levels),
+        Event::SetScheduling { scheduling } => instance.set_scheduling(scheduling),
+        Event::SetPreemption { preemption } => {
+            instance.set_preemption(preemption);
+            Ok(())
+        }
+        Event::SetWorkstationCapacity { id, capacity } => instance.set_workstation_capacity(id, capacity),
+        Event::AssignWorkstation { product_id, variant_id, workstation_id } =>
+            instance.assign_workstation(product_id, variant_id, workstation_id),
+        Event::AddSupply { material_id, amount } => instance.add_supply(material_id, amount),
+        Event::RemoveSupply { material_id, amount } => instance.remove_supply(material_id, amount),
+        Event::ReadSupply { material_id, amount, timestamp } => instance.read_supply(material_id, amount, timestamp),
+        Event::TakeFinished => {
+            instance.take_finished();
+            Ok(())
+        }
+        Event::Reset => {
+            instance.reset();
+            Ok(())
+        }
+        Event::Manufacture { .. } | Event::Deliver { .. } => Ok(()),
+    }
+}
@@ -16,29 +16,78 @@
 */
 
 mod internals;
+mod ffi;
+mod error;
+mod journal;
+mod notifications;
+mod persistence;
+mod scarcity;
+mod reservations;
+mod scheduling;
 
+pub use error::{Error, OrderOutcome};
+pub use journal::Event;
+pub use notifications::{Callback, Notification, Subscriber};
+pub use persistence::{Format, FORMAT_VERSION};
+pub use scarcity::{Proportional, ScarcityModel};
+pub use reservations::{Preemption, Reservation};
+pub use scheduling::Scheduling;
+
+use ffi::guard;
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::CString;
+use std::io::{BufRead, Read, Write};
+use journal::Journal;
+use notifications::Subscribers;
+use scarcity::{ModelRef, Models};
+use reservations::Reservations;
+use std::sync::Arc;
+
+/// Priority levels of a new instance.
 const PRIORITIES: usize = 4;
-const EQUILIBRIUM: f64 = 50.0;
+/// Scarcity of a material whose demand is in balance with its supply.
+pub const EQUILIBRIUM: f64 = 50.0;
+/// Subscribers are warned when less than this share of what a material ever had is left.
+pub const DEPLETION_WARNING: f64 = 0.1;
 pub const VERBOSITY_QUIET: usize = 0;
-pub const VERBOSITY_RESULTS: usize = 1; // not used in the lib
+// the lib prints nothing, verbosity is up to the host; see `Instance::subscribe`
+pub const VERBOSITY_RESULTS: usize = 1;
 pub const VERBOSITY_INNER: usize = 2;
-pub const VERBOSITY_FAILURES: usize = 3; // not used in the lib; should be replaced by callbacks
+pub const VERBOSITY_FAILURES: usize = 3;
+
+type Materials = BTreeMap<usize, Material>;
+type Workstations = BTreeMap<usize, Workstation>;
+type Products = BTreeMap<usize, Product>;
 
 //#[derive(Debug)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Product {
-    //name: String,
+    #[serde(with = "persistence::c_string")]
+    name: CString,
+    #[serde(with = "persistence::optional_c_string")]
+    description: Option<CString>,
     pub variants: Vec<ProductVariant>,
     //scarcity: usize,
     pub supply: f64,
     pub demand: f64,
     pub priority: usize,
+    /// Retired products cannot be ordered; queued orders are still manufactured.
+    pub retired: bool,
+    next_variant_id: usize,
 }
 
 impl Product {
-    fn manufacture(&mut self, material: &mut Material, amount: f64, variant: &ProductVariant) {
-        let material_amount = variant.components.material_amount * amount;
-        material.supply -= material_amount;
-        material.demand -= material_amount;
+    pub fn name(&self) -> &str {
+        self.name.to_str().unwrap()
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_ref().map(|d| d.to_str().unwrap())
+    }
+
+    fn manufacture(&mut self, amount: f64) {
         self.supply += amount;
         //product.demand -= amount;
     }
@@ -57,322 +106,1803 @@ impl Product {
         self.demand -= amount;
     }
 
-    pub fn get_variant(&self, variant_id: usize) -> &ProductVariant {
-        self.variants.iter().find(|x| x.id == variant_id).unwrap()
+    /// The variant of the given id, `None` if the product has no such variant.
+    pub fn get_variant(&self, variant_id: usize) -> Option<&ProductVariant> {
+        self.variants.iter().find(|x| x.id == variant_id)
     }
 }
 
 //#[derive(Debug)]
-#[derive(Copy, Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct ProductVariant {
     id: usize,
-    pub components: Component,
-    //change to vec in the future
+    pub components: Vec<Component>,
     work_complexity: f64,
+    retired: bool,
+    /// How long manufacturing takes, derived from `work_complexity` if not set.
+    #[serde(default)]
+    duration: Option<f64>,
+    /// Manufacturing uses up capacity of the workstation, unlimited without one.
+    #[serde(default)]
+    workstation: Option<usize>,
+}
+
+impl ProductVariant {
+    fn duration(&self, time_per_complexity: f64) -> f64 {
+        self.duration.unwrap_or(self.work_complexity * time_per_complexity)
+    }
+
+    fn is_available(&self, products: &Products, materials: &Materials, reservations: &Reservations,
+                    order_id: usize, priority: usize, amount: f64) -> bool {
+        self.components.iter().all(|c| {
+            let supply = match c.kind {
+                ComponentKind::Material => reservations.available(materials, order_id, priority, c.id),
+                ComponentKind::Product => products[&c.id].supply,
+            };
+            supply >= c.amount * amount
+        })
+    }
+
+    fn is_scarce(&self, products: &Products, materials: &Materials) -> bool {
+        self.components.iter().any(|c| match c.kind {
+            ComponentKind::Material => materials[&c.id].is_scarce(),
+            ComponentKind::Product => internals::get_product_scarcity(products, materials, c.id) > EQUILIBRIUM,
+        })
+    }
+
+    // negative amount releases the demand
+    fn add_demand(&self, products: &mut Products, materials: &mut Materials, amount: f64) {
+        for component in self.components.iter() {
+            match component.kind {
+                ComponentKind::Material => {
+                    let material = materials.get_mut(&component.id).unwrap();
+                    material.demand += component.amount * amount;
+                    material.scarcity_cache = material.get_scarcity();
+                }
+                ComponentKind::Product => products.get_mut(&component.id).unwrap().demand += component.amount * amount,
+            }
+        }
+    }
+
+    fn consume(&self, products: &mut Products, materials: &mut Materials, amount: f64, time: f64) {
+        for component in self.components.iter() {
+            let component_amount = component.amount * amount;
+            match component.kind {
+                ComponentKind::Material => {
+                    let material = materials.get_mut(&component.id).unwrap();
+                    material.supply -= component_amount;
+                    material.demand -= component_amount;
+                    material.consumed += component_amount;
+                    material.changed_at = Some(time);
+                }
+                ComponentKind::Product => products.get_mut(&component.id).unwrap().deliver(component_amount),
+            }
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum ComponentKind {
+    #[default]
+    Material,
+    Product,
 }
 
 #[repr(C)]
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Copy, Clone, PartialEq, Default, Serialize, Deserialize)]
 pub struct Component {
-    pub material_id: usize,
-    pub material_amount: f64,
+    pub kind: ComponentKind,
+    pub id: usize,
+    pub amount: f64,
+    #[serde(with = "persistence::float")]
     pub scarcity_cache: f64,
 }
 
 #[derive(Debug)]
-#[derive(Copy, Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Material {
+    #[serde(with = "persistence::c_string")]
+    name: CString,
+    #[serde(with = "persistence::optional_c_string")]
+    description: Option<CString>,
+    #[serde(with = "persistence::optional_c_string")]
+    unit: Option<CString>,
+    #[serde(with = "persistence::float")]
     pub scarcity_cache: f64,
     pub demand: f64,
     pub supply: f64,
-    //deposit_size: usize,
+    /// Part of the supply set aside for queued orders.
+    #[serde(default)]
+    pub reserved: f64,
+    /// Known reserves not extracted to the supply yet.
+    #[serde(default)]
+    pub deposit_size: f64,
+    /// Everything manufacturing has used up so far.
+    #[serde(default)]
+    pub consumed: f64,
+    /// Renewable materials regrow as time advances.
+    #[serde(default)]
+    pub regeneration: Option<Regeneration>,
+    /// Consumption per unit of time during the last `Instance::advance_time`, counting
+    /// from the end of the one before.
+    #[serde(default)]
+    pub harvest_rate: f64,
+    #[serde(default)]
+    consumed_at_advance: f64,
+    /// The last accepted supply reading.
+    #[serde(default)]
+    pub reading: Option<Reconciliation>,
+    #[serde(default)]
+    consumed_at_reading: f64,
+    // when the library last changed the supply itself
+    #[serde(default)]
+    changed_at: Option<f64>,
+    /// Products made of retired materials cannot be ordered; queued orders are still manufactured.
+    pub retired: bool,
+    // subscribers have been told the material is scarce
+    #[serde(default)]
+    scarce: bool,
+    // subscribers have been warned about the depletion
+    #[serde(default)]
+    depleting: bool,
+    /// Name of the model selected for the material, the instance's default otherwise.
+    #[serde(default)]
+    scarcity_model: Option<String>,
+    #[serde(skip)]
+    model: ModelRef,
 }
 
 impl Material {
+    pub fn name(&self) -> &str {
+        self.name.to_str().unwrap()
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_ref().map(|d| d.to_str().unwrap())
+    }
+
+    pub fn unit(&self) -> Option<&str> {
+        self.unit.as_ref().map(|u| u.to_str().unwrap())
+    }
+
+    /// Name of the `ScarcityModel` the material uses.
+    pub fn scarcity_model(&self) -> &str {
+        self.model.0.name()
+    }
+
     #[must_use]
-    pub extern fn get_scarcity(&self) -> f64 { //100/2=50
-        if self.supply != 0.0 {
-            self.demand * EQUILIBRIUM / (self.supply /*+ self.deposit_size*/)
-        } else { std::f64::INFINITY }
+    pub extern "C" fn get_scarcity(&self) -> f64 {
+        self.model.0.scarcity(self)
+    }
+
+    pub fn is_scarce(&self) -> bool {
+        self.model.0.is_scarce(self)
+    }
+
+    /// Supply not reserved for queued orders.
+    pub fn available_to_promise(&self) -> f64 {
+        self.supply - self.reserved
+    }
+
+    /// Supply and deposit together.
+    pub fn remaining(&self) -> f64 {
+        self.supply + self.deposit_size
+    }
+
+    /// Whether more is being consumed than regrows.
+    pub fn is_overharvested(&self) -> bool {
+        self.regeneration.is_some_and(|r| self.harvest_rate > r.rate)
+    }
+
+    fn update_harvest_rate(&mut self, duration: f64) {
+        self.harvest_rate = (self.consumed - self.consumed_at_advance) / duration;
+        self.consumed_at_advance = self.consumed;
+    }
+
+    fn regenerate(&mut self, duration: f64, time: f64) {
+        if let Some(r) = self.regeneration {
+            if self.supply < r.capacity && duration > 0.0 {
+                self.supply = (self.supply + r.rate * duration).min(r.capacity);
+                self.changed_at = Some(time);
+            }
+        }
     }
+
+    fn is_depleting(&self) -> bool {
+        self.consumed > 0.0 && self.remaining() < DEPLETION_WARNING * (self.remaining() + self.consumed)
+    }
+}
+
+/// Manufacturing capacity in work-complexity units per unit of time.
+#[derive(Clone, Serialize, Deserialize)]
+struct Workstation {
+    capacity: f64,
+    // used up in the current unit of time
+    used: f64,
+    total_used: f64,
+    // capacity of the past units of time
+    total_capacity: f64,
+}
+
+impl Workstation {
+    // an order larger than the whole capacity gets an idle workstation for itself
+    fn fits(&self, work: f64) -> bool {
+        self.used + work <= self.capacity || self.used == 0.0
+    }
+
+    fn utilisation(&self, workstation_id: usize) -> Utilisation {
+        Utilisation {
+            workstation_id,
+            capacity: self.capacity,
+            used: self.used,
+            utilisation: self.used / self.capacity,
+            average: self.total_used / (self.total_capacity + self.capacity),
+        }
+    }
+}
+
+/// How much of its capacity a workstation uses.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Default)]
+pub struct Utilisation {
+    pub workstation_id: usize,
+    pub capacity: f64,
+    /// Work complexity started in the current unit of time.
+    pub used: f64,
+    /// Share of the capacity used in the current unit of time, above 1 for orders larger than the capacity.
+    pub utilisation: f64,
+    /// Share of the capacity used since the workstation was added.
+    pub average: f64,
+}
+
+/// Supply grows by `rate` per unit of time up to `capacity`, consuming more than `rate`
+/// is not sustainable.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Regeneration {
+    pub rate: f64,
+    pub capacity: f64,
+}
+
+/// Supply reading of a material compared with what the library expected from its own ledger.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct Reconciliation {
+    pub material_id: usize,
+    pub timestamp: f64,
+    pub reading: f64,
+    /// Supply before the reading: the previous reading plus deltas and extraction, minus consumption.
+    pub expected: f64,
+    /// Consumed by manufacturing since the previous reading.
+    pub consumed: f64,
+    /// Differences of all readings so far; losses are negative.
+    pub total_discrepancy: f64,
+}
+
+/// Which variants may be manufactured when the preferred one cannot be.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Substitution {
+    Never,
+    Any,
+    /// Only variants whose production efficiency is at most given percent worse than the preferred one's.
+    WithinEfficiency(f64),
+    /// Only the listed variant ids.
+    Only(Vec<usize>),
+}
+
+/// Kind of a `CSubstitution`, named like the `Substitution` it stands for.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SubstitutionKind {
+    Never,
+    Any,
+    WithinEfficiency,
+    Only,
 }
 
+/// `Substitution` for C: `percent` is read for `WithinEfficiency`, the `variant_count`
+/// ids at `variant_ids` for `Only`.
 #[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct CSubstitution {
+    pub kind: SubstitutionKind,
+    pub percent: f64,
+    pub variant_ids: *const usize,
+    pub variant_count: usize,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Order {
+    id: usize,
     product_id: usize,
     product_amount: f64,
     preferred_variant: usize,
     user_id: usize,
-    allow_substitution: bool,
+    substitution: Substitution,
+    for_stock: bool,
+    #[serde(default)]
+    options: OrderOptions,
+    #[serde(default)]
+    queued_at: f64,
+    /// The order a sub-order to stock was scheduled for.
+    #[serde(default)]
+    parent: Option<usize>,
+}
+
+impl Order {
+    fn due(&self) -> Option<f64> {
+        self.options.due.into_option()
+    }
 }
 
+/// How an order may be fulfilled besides in one piece from a single variant.
 #[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct OrderOptions {
+    /// Deliver what can be made now in parts and keep the rest queued.
+    pub partial: bool,
+    /// Make the order from several variants when no single one can cover it, as far as
+    /// the substitution allows.
+    pub split: bool,
+    /// Priority level to queue the order at instead of the product's.
+    #[serde(default)]
+    pub priority: COption<usize>,
+    /// Time the order should be finished by; within a level, orders with the earliest
+    /// due date go first and those without one last.
+    #[serde(default)]
+    pub due: COption<f64>,
+}
+
+/// An order that was still queued or being manufactured when its due date passed.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct MissedDeadline {
+    pub order_id: usize,
+    pub product_id: usize,
+    pub user_id: usize,
+    pub due: f64,
+}
+
+// an order whose materials have been consumed, waiting for its completion time
+#[derive(Clone, Serialize, Deserialize)]
+struct Production {
+    order: Order,
+    variant_id: usize,
+    completes_at: f64,
+}
+
+#[repr(C)]
+#[derive(Default)]
+pub struct FinishedOrder {
+    pub order_id: usize,
+    pub product_id: usize,
+    pub product_amount: f64,
+    pub preferred_variant: usize,
+    pub user_id: usize,
+    pub allow_substitution: bool,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum OrderState {
+    #[default]
+    Queued,
+    /// Sub-products needed for the order or the order itself are being manufactured.
+    InProduction,
+    Finished,
+    Rejected,
+    Cancelled,
+}
+
+#[repr(C)]
+#[derive(Default)]
+pub struct OrderStatus {
+    pub state: OrderState,
+    /// Priority queue of a queued order.
+    pub priority: usize,
+    /// Position in the priority queue of a queued order.
+    pub position: usize,
+    /// Completion time of an order being manufactured.
+    pub completes_at: f64,
+}
+
+/// `data` is zeroed when `is_some` is false.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct COption<T> {
-    is_some: bool,
-    data: T,
+    pub is_some: bool,
+    pub data: T,
+}
+
+impl<T: Default> COption<T> {
+    fn none() -> Self {
+        COption { is_some: false, data: T::default() }
+    }
+}
+
+impl<T: Default> From<Option<T>> for COption<T> {
+    fn from(option: Option<T>) -> Self {
+        match option {
+            Some(data) => COption { is_some: true, data },
+            None => COption::none(),
+        }
+    }
+}
+
+impl<T: Default> Default for COption<T> {
+    fn default() -> Self {
+        COption::none()
+    }
 }
 
+impl<T> COption<T> {
+    pub fn into_option(self) -> Option<T> {
+        if self.is_some { Some(self.data) } else { None }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Instance {
-    materials: Vec<Material>,
-    products: Vec<Product>,
-    production_queue: [Vec<Order>; PRIORITIES],
+    materials: Materials,
+    products: Products,
+    next_material_id: usize,
+    next_product_id: usize,
+    /// One queue per priority level.
+    production_queue: Vec<Vec<Order>>,
+    #[serde(default)]
+    scheduling: Scheduling,
     finished_products: Vec<Order>,
+    order_states: HashMap<usize, OrderState>,
+    next_order_id: usize,
+    #[serde(default)]
+    in_production: Vec<Production>,
+    #[serde(default)]
+    time: f64,
+    #[serde(default)]
+    time_per_complexity: f64,
+    #[serde(default)]
+    workstations: Workstations,
+    #[serde(default)]
+    next_workstation_id: usize,
+    #[serde(default)]
+    reservations: Reservations,
+    #[serde(default)]
+    missed_deadlines: Vec<MissedDeadline>,
     pub verbose: usize,
+    #[serde(skip)]
+    journal: Journal,
+    #[serde(skip)]
+    subscribers: Subscribers,
+    #[serde(skip)]
+    scarcity_models: Models,
 }
 
 impl Instance {
-    pub fn get_product(&self, id: usize) -> &Product {
-        &self.products[id]
+    fn new_order_id(&mut self) -> usize {
+        self.next_order_id += 1;
+        self.next_order_id - 1
     }
 
-    pub fn get_products(&self) -> &Vec<Product> {
+    pub fn get_product(&self, id: usize) -> Option<&Product> {
+        self.products.get(&id)
+    }
+
+    pub fn get_products(&self) -> &BTreeMap<usize, Product> {
         &self.products
     }
 
-    pub fn get_material(&self, id: usize) -> &Material {
-        &self.materials[id]
+    pub fn get_material(&self, id: usize) -> Option<&Material> {
+        self.materials.get(&id)
     }
 
-    pub fn get_materials(&self) -> &Vec<Material> {
+    pub fn get_materials(&self) -> &BTreeMap<usize, Material> {
         &self.materials
     }
+
+    pub fn find_material(&self, name: &str) -> Option<usize> {
+        self.materials.iter().find(|(_, m)| m.name() == name.trim()).map(|(id, _)| *id)
+    }
+
+    pub fn find_product(&self, name: &str) -> Option<usize> {
+        self.products.iter().find(|(_, p)| p.name() == name.trim()).map(|(id, _)| *id)
+    }
 }
 
 #[no_mangle]
-pub extern fn init() -> Box<Instance> {
-    Box::from(Instance {
-        materials: Vec::new(),
-        products: Vec::new(),
-        production_queue: [Vec::new(), Vec::new(), Vec::new(), Vec::new()],
-        finished_products: Vec::new(),
-        verbose: 0,
-    })
+pub extern "C" fn init() -> Box<Instance> {
+    Box::new(Instance::new())
 }
 
+/// Frees an instance returned by `init` or `clone_instance`. Null is ignored.
 #[no_mangle]
-pub extern fn add_material(instance: &mut Instance, supply: f64) -> u8 {
-    const ZERO_SUPPLY: u8 = 2;
-    //const DUPLICATE_MATERIAL: u8 = 3;
-
-    if supply <= 0.0 { return ZERO_SUPPLY; }
+pub extern "C" fn destroy(instance: Option<Box<Instance>>) {
+    drop(instance);
+}
 
-    instance.materials.push(Material{
-            scarcity_cache: 0.0,
-            demand: 0.0,
-            supply,
-        });
-        0 //ok
+/// Clears all data of the instance but keeps its settings. Null is ignored.
+#[no_mangle]
+pub extern "C" fn reset(instance: Option<&mut Instance>) {
+    if let Some(instance) = instance { instance.reset(); }
 }
 
+/// Deep copy of the instance, e.g. to try out orders without affecting the original.
+/// Returns null for null; free the copy with `destroy`.
 #[no_mangle]
-pub extern fn add_product(instance: &mut Instance, material_id: usize, material_amount: f64, priority: usize, work_complexity: f64) -> u8 {
-    const ZERO_MATERIAL: u8 = 3;
-    const NO_SUCH_MATERIAL: u8 = 4;
-    //const DUPLICATE_PRODUCT: u8 = 5;
+pub extern "C" fn clone_instance(instance: Option<&Instance>) -> Option<Box<Instance>> {
+    guard(None, || instance.map(|i| Box::new(i.clone())))
+}
 
-    if material_amount <= 0.0 { return ZERO_MATERIAL; }
-    if instance.materials.len() <= material_id { return NO_SUCH_MATERIAL; }
-    instance.products.push(Product {
-        //name,
-        variants: vec![ProductVariant {
-            id: 0,
-            components: Component { material_id, material_amount, scarcity_cache: 0.0 },
-            work_complexity,
-        }],
-        supply: 0.0,
-        demand: 0.0,
-        priority,
-    });
-    0
+// names are stored trimmed and as C strings so that they can be handed over to C as they are
+fn to_name(name: &str) -> Option<CString> {
+    let name = name.trim();
+    if name.is_empty() { return None; }
+    CString::new(name).ok()
 }
 
-#[no_mangle]
-pub extern fn order_product(instance: &mut Instance,
+fn to_description(description: Option<&str>) -> Result<Option<CString>, Error> {
+    description.map(|d| CString::new(d).map_err(|_| Error::InvalidName)).transpose()
+}
+
+// NaN and infinity are no amounts either
+fn positive(amount: f64) -> bool {
+    amount.is_finite() && amount > 0.0
+}
+
+fn non_negative(amount: f64) -> bool {
+    amount.is_finite() && amount >= 0.0
+}
+
+// orders with values the journal cannot hold are rejected before they are recorded
+fn check_order_values(amount: f64, substitution: &Substitution, due: Option<f64>) -> Result<(), Error> {
+    if !positive(amount) { return Err(Error::ZeroAmount); }
+    if let Substitution::WithinEfficiency(percent) = substitution {
+        if !non_negative(*percent) { return Err(Error::ZeroAmount); }
+    }
+    if due.is_some_and(|due| !due.is_finite()) { return Err(Error::PastTime); }
+    Ok(())
+}
+
+impl Default for Instance {
+    fn default() -> Self {
+        Instance::new()
+    }
+}
+
+impl Instance {
+    pub fn new() -> Instance {
+        Instance {
+            materials: BTreeMap::new(),
+            products: BTreeMap::new(),
+            next_material_id: 0,
+            next_product_id: 0,
+            production_queue: vec![Vec::new(); PRIORITIES],
+            scheduling: Scheduling::Strict,
+            finished_products: Vec::new(),
+            order_states: HashMap::new(),
+            next_order_id: 0,
+            in_production: Vec::new(),
+            time: 0.0,
+            time_per_complexity: 0.0,
+            workstations: BTreeMap::new(),
+            next_workstation_id: 0,
+            reservations: Reservations::default(),
+            missed_deadlines: Vec::new(),
+            verbose: 0,
+            journal: Journal::default(),
+            subscribers: Subscribers::default(),
+            scarcity_models: Models::default(),
+        }
+    }
+
+    /// Writes the complete state of the instance, see `Format` for the layout.
+    pub fn save<W: Write>(&self, writer: W, format: Format) -> Result<(), Error> {
+        persistence::save(self, writer, format)
+    }
+
+    /// Reads an instance written by `save` of this or an older version of the library.
+    /// Instances referring to missing materials, products or variants are rejected.
+    pub fn load<R: Read>(reader: R, format: Format) -> Result<Instance, Error> {
+        persistence::load(reader, format)
+    }
+
+    /// Removes all materials, products and orders; settings like `verbose`, the scarcity
+    /// models and the scheduling are kept.
+    pub fn reset(&mut self) {
+        let journal = std::mem::take(&mut self.journal);
+        let subscribers = std::mem::take(&mut self.subscribers);
+        let scarcity_models = std::mem::take(&mut self.scarcity_models);
+        let scheduling = std::mem::take(&mut self.scheduling);
+        let mut reservations = Reservations::default();
+        reservations.preemption = self.reservations.preemption;
+        *self = Instance {
+            verbose: self.verbose, time_per_complexity: self.time_per_complexity,
+            production_queue: vec![Vec::new(); self.production_queue.len()],
+            scheduling, reservations, journal, subscribers, scarcity_models, ..Instance::new()
+        };
+        self.journal.record(Event::Reset);
+    }
+
+    /// Appends every following change of the instance to `writer`, one JSON line per `Event`.
+    /// To be able to replay it, start the journal on an empty instance.
+    pub fn start_journal<W: Write + Send + 'static>(&mut self, writer: W) {
+        self.journal = Journal::new(writer);
+    }
+
+    pub fn stop_journal(&mut self) {
+        self.journal = Journal::default();
+    }
+
+    /// False also once writing to the journal has failed.
+    pub fn is_journaling(&self) -> bool {
+        self.journal.is_active()
+    }
+
+    /// Registers a subscriber to be notified about what happens from now on;
+    /// returns the id to unsubscribe with.
+    pub fn subscribe<S: Subscriber + 'static>(&mut self, subscriber: S) -> usize {
+        self.subscribers.add(Box::new(subscriber))
+    }
+
+    pub fn unsubscribe(&mut self, id: usize) -> Result<(), Error> {
+        if self.subscribers.remove(id) { Ok(()) } else { Err(Error::NoSuchSubscriber) }
+    }
+
+    /// Rebuilds an instance from a journal started on an empty instance.
+    pub fn replay<R: BufRead>(reader: R) -> Result<Instance, Error> {
+        Instance::new().replay_on(reader)
+    }
+
+    /// Like `replay`, but onto this empty instance, e.g. one configured with the scarcity
+    /// models the journal was recorded with.
+    pub fn replay_on<R: BufRead>(self, reader: R) -> Result<Instance, Error> {
+        journal::replay(self, reader)
+    }
+
+    /// Makes `model` the one used by materials without a model of their own.
+    pub fn set_default_scarcity_model<M: ScarcityModel + 'static>(&mut self, model: M) {
+        let model = ModelRef(Arc::new(model));
+        self.scarcity_models.add(model.clone());
+        self.scarcity_models.default = model.clone();
+        for material in self.materials.values_mut().filter(|m| m.scarcity_model.is_none()) {
+            material.model = model.clone();
+        }
+        self.refresh_scarcity();
+    }
+
+    /// Makes `model` selectable by its name, replacing a model of the same name.
+    /// Materials of a loaded instance use their models once these are added again.
+    pub fn add_scarcity_model<M: ScarcityModel + 'static>(&mut self, model: M) {
+        let model = ModelRef(Arc::new(model));
+        self.scarcity_models.add(model.clone());
+        let name = model.0.name();
+        for material in self.materials.values_mut().filter(|m| m.scarcity_model.as_deref() == Some(name)) {
+            material.model = model.clone();
+        }
+        self.refresh_scarcity();
+    }
+
+    /// Selects an added model for the material by name, `None` for the default one.
+    pub fn set_scarcity_model(&mut self, material_id: usize, name: Option<&str>) -> Result<(), Error> {
+        let model = match name {
+            Some(name) => self.scarcity_models.get(name).ok_or(Error::NoSuchScarcityModel)?,
+            None => self.scarcity_models.default.clone(),
+        };
+        let material = self.materials.get_mut(&material_id).ok_or(Error::NoSuchMaterial)?;
+        material.scarcity_model = name.map(String::from);
+        material.model = model;
+        self.refresh_scarcity();
+        self.journal.record(Event::SetScarcityModel { material_id, name: name.map(String::from) });
+        Ok(())
+    }
+
+    fn refresh_scarcity(&mut self) {
+        for material in self.materials.values_mut() {
+            material.scarcity_cache = material.get_scarcity();
+        }
+        let ids: Vec<usize> = self.products.keys().cloned().collect();
+        for id in ids {
+            internals::refresh_scarcity(&mut self.products, &mut self.materials, id);
+        }
+        internals::notify_materials(&mut self.materials, &mut self.subscribers);
+    }
+
+    /// Returns the id of the new material; ids are never reused.
+    /// Material names are unique.
+    pub fn add_material(&mut self, name: &str, supply: f64) -> Result<usize, Error> {
+        let name = to_name(name).ok_or(Error::InvalidName)?;
+        if !positive(supply) { return Err(Error::ZeroAmount); }
+        if self.materials.values().any(|m| m.name == name) { return Err(Error::DuplicateMaterial); }
+
+        let material_id = self.next_material_id;
+        self.next_material_id += 1;
+        self.materials.insert(material_id, Material{
+                name,
+                description: None,
+                unit: None,
+                scarcity_cache: 0.0,
+                demand: 0.0,
+                supply,
+                reserved: 0.0,
+                deposit_size: 0.0,
+                consumed: 0.0,
+                regeneration: None,
+                harvest_rate: 0.0,
+                consumed_at_advance: 0.0,
+                reading: None,
+                consumed_at_reading: 0.0,
+                changed_at: None,
+                retired: false,
+                scarce: false,
+                depleting: false,
+                scarcity_model: None,
+                model: self.scarcity_models.default.clone(),
+            });
+        let name = self.materials[&material_id].name().to_string();
+        self.journal.record(Event::AddMaterial { id: material_id, name, supply });
+        Ok(material_id)
+    }
+
+    /// Returns the id of the new product; ids are never reused.
+    /// The product starts with a single variant of id 0. Product names are unique.
+    pub fn add_product(&mut self, name: &str, material_id: usize, material_amount: f64, priority: usize, work_complexity: f64) -> Result<usize, Error> {
+        let name = to_name(name).ok_or(Error::InvalidName)?;
+        if !positive(material_amount) || !non_negative(work_complexity) { return Err(Error::ZeroAmount); }
+        if priority >= self.production_queue.len() { return Err(Error::InvalidPriority); }
+        if !self.materials.contains_key(&material_id) { return Err(Error::NoSuchMaterial); }
+        if self.products.values().any(|p| p.name == name) { return Err(Error::DuplicateProduct); }
+        let product_id = self.next_product_id;
+        self.next_product_id += 1;
+        self.products.insert(product_id, Product {
+            name,
+            description: None,
+            variants: vec![ProductVariant {
+                id: 0,
+                components: vec![Component {
+                    kind: ComponentKind::Material,
+                    id: material_id,
+                    amount: material_amount,
+                    scarcity_cache: 0.0,
+                }],
+                work_complexity,
+                retired: false,
+                duration: None,
+                workstation: None,
+            }],
+            supply: 0.0,
+            demand: 0.0,
+            priority,
+            retired: false,
+            next_variant_id: 1,
+        });
+        let name = self.products[&product_id].name().to_string();
+        self.journal.record(Event::AddProduct { id: product_id, name, material_id, material_amount, priority, work_complexity });
+        Ok(product_id)
+    }
+
+    /// Returns the id of the order and whether it was delivered from stock or queued.
+    /// A rejected order gets an id as well and is reported as `OrderState::Rejected`.
+    pub fn order_product(&mut self,
+                         id: usize,
+                         amount: f64,
+                         variant_id: usize,
+                         user_id: usize,
+                         substitution: Substitution) -> Result<(usize, OrderOutcome), Error>
+    {
+        self.order_product_with(id, amount, variant_id, user_id, substitution, OrderOptions::default())
+    }
+
+    /// Like `order_product`, see `OrderOptions`. Due dates must be finite and not in the past.
+    pub fn order_product_with(&mut self,
+                              id: usize,
+                              amount: f64,
+                              variant_id: usize,
+                              user_id: usize,
+                              substitution: Substitution,
+                              options: OrderOptions) -> Result<(usize, OrderOutcome), Error>
+    {
+        let new_id = self.new_order_id();
+        self.order_states.insert(new_id, OrderState::Rejected);
+        if let Err(e) = check_order_values(amount, &substitution, options.due.into_option()) {
+            self.journal.record(Event::RejectOrder { order_id: new_id });
+            return Err(e);
+        }
+        self.journal.record(Event::OrderProduct {
+            order_id: new_id, product_id: id, amount, variant_id, user_id, substitution: substitution.clone(), options,
+        });
+
+        self.check_orderable(id, variant_id)?;
+        if options.priority.into_option().is_some_and(|p| p >= self.production_queue.len()) {
+            return Err(Error::InvalidPriority);
+        }
+        if options.due.into_option().is_some_and(|due| due < self.time) { return Err(Error::PastTime); }
+
+        let order = Order {
+            id: new_id,
+            product_id: id,
+            product_amount: amount,
+            preferred_variant: variant_id,
+            user_id,
+            substitution,
+            for_stock: false,
+            options,
+            queued_at: self.time,
+            parent: None,
+        };
+        let product = self.products.get_mut(&id).unwrap();
+        product.demand += amount;
+
+        // serve from the finished goods inventory first
+        let delivered = product.supply.min(amount);
+        if delivered > 0.0 {
+            product.deliver(delivered);
+            self.journal.record(Event::Deliver { order_id: new_id, product_id: id, amount: delivered });
+            self.finished_products.push(Order { product_amount: delivered, ..order.clone() });
+        }
+        if delivered == amount {
+            self.order_states.insert(new_id, OrderState::Finished);
+            self.subscribers.notify(Notification::OrderFinished { order_id: new_id, product_id: id, amount, user_id });
+            return Ok((new_id, OrderOutcome::Delivered));
+        }
+
+        Ok((new_id, self.queue_order(Order { product_amount: amount - delivered, ..order })))
+    }
+
+    /// Queues production of a product to the finished goods inventory ahead of any demand.
+    pub fn produce_to_stock(&mut self,
                             id: usize,
                             amount: f64,
                             variant_id: usize,
-                            user_id: usize,
-                            allow_substitution: bool) -> u8
-{
-    const OK_QUEUE: u8 = 1;
-    const CANNOT_ORDER_0_PRODUCTS: u8 = 2;
-    const NO_SUCH_MATERIAL: u8 = 3;
-    const MATERIAL_NOT_AVAIL: u8 = 4;
-    const MATERIAL_SCARCE: u8 = 5;
-
-    if amount <= 0.0 { return CANNOT_ORDER_0_PRODUCTS; }
-    let products = &mut instance.products;
-    if products.is_empty() { panic!("no products in database"); }
-    let mut prod = products.remove(id);
-
-    let variant = *prod.get_variant(variant_id);
-    let production_queue = &mut instance.production_queue;
-    let mut material;
-    if instance.materials.len() > variant.components.material_id {
-        material = instance.materials.remove(variant.components.material_id)
-    } else { return NO_SUCH_MATERIAL }
-    /*let mut material = match instance.materials.remove(variant.components.material_id) {
-        Some(m) => m,
-        None => return NO_SUCH_MATERIAL, //No such material in database.
-    };*/
-    prod.demand += amount;
-    material.demand += amount * variant.components.material_amount;
-    material.scarcity_cache = material.get_scarcity();
-
-    let mut code = OK_QUEUE;
-    if amount <= prod.supply {
-        prod.deliver(amount);
-        //code = 0; //ok, already manufactured
-        panic!("cannot happen right now");
-    } else {
-        if material.supply < (amount * variant.components.material_amount) //only a dev safeguard
-        {
-            code = MATERIAL_NOT_AVAIL; //Material not available.
-        }
-        if material.scarcity_cache > EQUILIBRIUM
-        {
-            code = MATERIAL_SCARCE; //Material scarce.
+                            substitution: Substitution) -> Result<(usize, OrderOutcome), Error>
+    {
+        let new_id = self.new_order_id();
+        self.order_states.insert(new_id, OrderState::Rejected);
+        if let Err(e) = check_order_values(amount, &substitution, None) {
+            self.journal.record(Event::RejectOrder { order_id: new_id });
+            return Err(e);
         }
+        self.journal.record(Event::ProduceToStock {
+            order_id: new_id, product_id: id, amount, variant_id, substitution: substitution.clone(),
+        });
+
+        self.check_orderable(id, variant_id)?;
 
-        production_queue[prod.priority].push(Order {
+        let outcome = self.queue_order(Order {
+            id: new_id,
             product_id: id,
             product_amount: amount,
             preferred_variant: variant_id,
-            user_id,
-            allow_substitution,
+            user_id: 0,
+            substitution,
+            for_stock: true,
+            options: OrderOptions::default(),
+            queued_at: self.time,
+            parent: None,
+        });
+        Ok((new_id, outcome))
+    }
+
+    fn check_orderable(&self, id: usize, variant_id: usize) -> Result<(), Error> {
+        let product = self.products.get(&id).ok_or(Error::NoSuchProduct)?;
+        let variant = product.variants.iter().find(|v| v.id == variant_id).ok_or(Error::NoSuchVariant)?;
+        for c in variant.components.iter() {
+            match c.kind {
+                ComponentKind::Material => if !self.materials.contains_key(&c.id) { return Err(Error::NoSuchMaterial); },
+                ComponentKind::Product => if !self.products.contains_key(&c.id) { return Err(Error::NoSuchProduct); },
+            }
+        }
+        let retired = product.retired || variant.retired || variant.components.iter().any(|c| match c.kind {
+            ComponentKind::Material => self.materials[&c.id].retired,
+            ComponentKind::Product => self.products[&c.id].retired,
+        });
+        if retired { return Err(Error::Retired); }
+        Ok(())
+    }
+
+    fn queue_order(&mut self, order: Order) -> OrderOutcome {
+        let products = &mut self.products;
+        let materials = &mut self.materials;
+        let variant = products[&order.product_id].get_variant(order.preferred_variant).unwrap().clone();
+        variant.add_demand(products, materials, order.product_amount);
+        let priority = order.options.priority.into_option().unwrap_or(products[&order.product_id].priority);
+        self.reservations.reserve(materials, order.id, priority, &variant, order.product_amount);
+
+        let mut outcome = OrderOutcome::Queued;
+        // sub-products do not need to be in stock as long as they can be made
+        if !internals::can_supply(products, materials, &variant, order.product_amount) //only a dev safeguard
+        {
+            outcome = OrderOutcome::NotAvailable;
+        }
+        if variant.is_scarce(products, materials)
+        {
+            outcome = OrderOutcome::Scarce;
+        }
+        self.order_states.insert(order.id, OrderState::Queued);
+        self.subscribers.notify(Notification::OrderQueued {
+            order_id: order.id, product_id: order.product_id, amount: order.product_amount, priority, for_stock: order.for_stock,
+        });
+        let q = &mut self.production_queue[priority];
+        let position = order.due()
+            .and_then(|due| q.iter().position(|o| o.due().is_none_or(|d| d > due)))
+            .unwrap_or(q.len());
+        q.insert(position, order);
+
+        if outcome != OrderOutcome::Queued {
+            internals::process_queue(self);
+        } else {
+            internals::notify_materials(&mut self.materials, &mut self.subscribers);
+        }
+
+        outcome
+    }
+
+    /// Removes a queued order and releases the demand it has added, along with the sub-orders
+    /// still queued for it. Orders being manufactured have used up their materials already
+    /// and cannot be cancelled.
+    pub fn cancel_order(&mut self, order_id: usize) -> Result<(), Error> {
+        if !self.order_states.contains_key(&order_id) { return Err(Error::NoSuchOrder); }
+        if !self.production_queue.iter().flatten().any(|o| o.id == order_id) { return Err(Error::NotQueued); }
+        let sub_orders: Vec<usize> = self.production_queue.iter().flatten()
+            .filter(|o| o.parent == Some(order_id))
+            .map(|o| o.id)
+            .collect();
+        for id in sub_orders {
+            let _ = self.cancel_order(id);
+        }
+        let q = self.production_queue.iter_mut().find(|q| q.iter().any(|o| o.id == order_id)).unwrap();
+        let index = q.iter().position(|o| o.id == order_id).unwrap();
+        let order = q.remove(index);
+
+        let products = &mut self.products;
+        if !order.for_stock { products.get_mut(&order.product_id).unwrap().demand -= order.product_amount; }
+        let variant = products[&order.product_id].get_variant(order.preferred_variant).unwrap().clone();
+        variant.add_demand(products, &mut self.materials, -order.product_amount);
+        self.reservations.release(&mut self.materials, order_id);
+        self.order_states.insert(order_id, OrderState::Cancelled);
+        internals::notify_materials(&mut self.materials, &mut self.subscribers);
+        self.journal.record(Event::CancelOrder { order_id });
+        Ok(())
+    }
+
+    pub fn get_order_status(&self, order_id: usize) -> Option<OrderStatus> {
+        let mut status = OrderStatus { state: *self.order_states.get(&order_id)?, ..OrderStatus::default() };
+        if let Some(production) = self.in_production.iter().find(|p| p.order.id == order_id) {
+            status.completes_at = production.completes_at;
+        }
+        for (priority, q) in self.production_queue.iter().enumerate() {
+            if let Some(position) = q.iter().position(|o| o.id == order_id) {
+                status.priority = priority;
+                status.position = position;
+            }
+        }
+        Some(status)
+    }
+
+    pub fn process_queue(&mut self) {
+        self.journal.record(Event::ProcessQueue);
+        internals::process_queue(self);
+    }
+
+    /// Hands over the most recently finished order.
+    pub fn take_finished(&mut self) -> Option<FinishedOrder> {
+        let order = self.finished_products.pop()?;
+        self.journal.record(Event::TakeFinished);
+        Some(FinishedOrder {
+            order_id: order.id,
+            product_id: order.product_id,
+            product_amount: order.product_amount,
+            preferred_variant: order.preferred_variant,
+            user_id: order.user_id,
+            allow_substitution: order.substitution != Substitution::Never,
+        })
+    }
+
+    /// Sets the supply of a material and manufactures what the new supply allows.
+    /// Use `read_supply` for readings that may be outdated by manufacturing.
+    pub fn update_supply(&mut self, id: usize, amount: f64) -> Result<(), Error> {
+        if !non_negative(amount) { return Err(Error::ZeroAmount); }
+        let result = match self.materials.get_mut(&id) {
+            Some(x) => {
+                x.supply = amount;
+                x.changed_at = Some(self.time);
+                Ok(())
+            }
+            None => Err(Error::NoSuchMaterial)
+        };
+        self.journal.record(Event::UpdateSupply { material_id: id, amount });
+        internals::process_queue(self);
+
+        result
+    }
+
+    /// Adds a delivery to the supply of a material and manufactures what the new supply allows.
+    pub fn add_supply(&mut self, id: usize, amount: f64) -> Result<(), Error> {
+        if !positive(amount) { return Err(Error::ZeroAmount); }
+        let material = self.materials.get_mut(&id).ok_or(Error::NoSuchMaterial)?;
+        material.supply += amount;
+        material.changed_at = Some(self.time);
+        self.journal.record(Event::AddSupply { material_id: id, amount });
+        internals::process_queue(self);
+        Ok(())
+    }
+
+    /// Takes a loss or an outside use off the supply of a material.
+    pub fn remove_supply(&mut self, id: usize, amount: f64) -> Result<(), Error> {
+        if !positive(amount) { return Err(Error::ZeroAmount); }
+        let material = self.materials.get_mut(&id).ok_or(Error::NoSuchMaterial)?;
+        if amount > material.supply { return Err(Error::InsufficientSupply); }
+        material.supply -= amount;
+        material.changed_at = Some(self.time);
+        material.scarcity_cache = material.get_scarcity();
+        self.journal.record(Event::RemoveSupply { material_id: id, amount });
+        internals::notify_materials(&mut self.materials, &mut self.subscribers);
+        Ok(())
+    }
+
+    /// Sets the supply of a material to a reading taken at `timestamp` of the simulation time
+    /// and manufactures what the new supply allows. Readings older than the last one or than
+    /// the last change of the supply by the library are rejected, as are readings without a finite
+    /// timestamp; a reading taken at the same time as a change is taken to include it.
+    pub fn read_supply(&mut self, id: usize, amount: f64, timestamp: f64) -> Result<(), Error> {
+        if !non_negative(amount) { return Err(Error::ZeroAmount); }
+        let material = self.materials.get_mut(&id).ok_or(Error::NoSuchMaterial)?;
+        let last_reading = material.reading.map_or(f64::NEG_INFINITY, |r| r.timestamp);
+        if !timestamp.is_finite() || timestamp < last_reading || material.changed_at.is_some_and(|t| timestamp < t) {
+            return Err(Error::StaleReading);
+        }
+
+        let total_discrepancy = material.reading.map_or(0.0, |r| r.total_discrepancy) + amount - material.supply;
+        material.reading = Some(Reconciliation {
+            material_id: id,
+            timestamp,
+            reading: amount,
+            expected: material.supply,
+            consumed: material.consumed - material.consumed_at_reading,
+            total_discrepancy,
+        });
+        material.consumed_at_reading = material.consumed;
+        material.supply = amount;
+        self.journal.record(Event::ReadSupply { material_id: id, amount, timestamp });
+        internals::process_queue(self);
+        Ok(())
+    }
+
+    /// The last reading of every material that has been read.
+    pub fn reconciliation_report(&self) -> Vec<Reconciliation> {
+        self.materials.values().filter_map(|m| m.reading).collect()
+    }
+
+    /// Sets the known reserves of a material beyond its supply.
+    pub fn update_deposit_size(&mut self, id: usize, size: f64) -> Result<(), Error> {
+        if !non_negative(size) { return Err(Error::ZeroAmount); }
+        let material = self.materials.get_mut(&id).ok_or(Error::NoSuchMaterial)?;
+        material.deposit_size = size;
+        material.scarcity_cache = material.get_scarcity();
+        self.journal.record(Event::UpdateDepositSize { material_id: id, size });
+        internals::notify_materials(&mut self.materials, &mut self.subscribers);
+        Ok(())
+    }
+
+    /// Moves up to `amount` of a material from its deposit to its supply and manufactures
+    /// what the new supply allows; returns the amount extracted.
+    pub fn extract(&mut self, id: usize, amount: f64) -> Result<f64, Error> {
+        if !positive(amount) { return Err(Error::ZeroAmount); }
+        let material = self.materials.get_mut(&id).ok_or(Error::NoSuchMaterial)?;
+        let extracted = amount.min(material.deposit_size);
+        material.deposit_size -= extracted;
+        material.supply += extracted;
+        material.changed_at = Some(self.time);
+        self.journal.record(Event::Extract { material_id: id, amount });
+        internals::process_queue(self);
+        Ok(extracted)
+    }
+
+    /// Makes the material renewable, or finite again with `None`.
+    pub fn set_regeneration(&mut self, id: usize, regeneration: Option<Regeneration>) -> Result<(), Error> {
+        if let Some(r) = regeneration {
+            if !positive(r.rate) || !positive(r.capacity) { return Err(Error::ZeroAmount); }
+        }
+        let material = self.materials.get_mut(&id).ok_or(Error::NoSuchMaterial)?;
+        material.regeneration = regeneration;
+        material.scarcity_cache = material.get_scarcity();
+        self.journal.record(Event::SetRegeneration { material_id: id, regeneration });
+        Ok(())
+    }
+
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    /// Lets renewable materials regrow for `duration` units of time, completes the orders
+    /// due by then and manufactures what the new supply allows.
+    pub fn advance_time(&mut self, duration: f64) -> Result<(), Error> {
+        if !positive(duration) { return Err(Error::ZeroAmount); }
+        self.journal.record(Event::AdvanceTime { duration });
+        self.advance(self.time + duration);
+        Ok(())
+    }
+
+    /// Advances the time by one unit.
+    pub fn tick(&mut self) {
+        let _ = self.advance_time(1.0);
+    }
+
+    /// Like `advance_time`, up to the given time.
+    pub fn advance_to(&mut self, time: f64) -> Result<(), Error> {
+        // there is no getting to NaN or infinity either
+        if !time.is_finite() || time <= self.time { return Err(Error::PastTime); }
+        self.journal.record(Event::AdvanceTo { time });
+        self.advance(time);
+        Ok(())
+    }
+
+    // stops at every completion and whole unit of time on the way, so that production
+    // freed by it starts on time and workstations get their capacity every unit of time
+    fn advance(&mut self, target: f64) {
+        let duration = target - self.time;
+        loop {
+            let next_unit = self.time.floor() + 1.0;
+            let next = self.in_production.iter().map(|p| p.completes_at).fold(target.min(next_unit), f64::min);
+            let step = next - self.time;
+            self.time = next;
+            for material in self.materials.values_mut() {
+                material.regenerate(step, next);
+            }
+            if next == next_unit {
+                for workstation in self.workstations.values_mut() {
+                    workstation.total_capacity += workstation.capacity;
+                    workstation.used = 0.0;
+                }
+            }
+            internals::complete_production(self);
+            self.refresh_scarcity();
+            internals::process_queue(self);
+            internals::check_deadlines(self);
+            if next >= target { break; }
+        }
+        // what was consumed on the way counts too
+        for material in self.materials.values_mut() {
+            material.update_harvest_rate(duration);
+        }
+    }
+
+    /// Returns the id of the new workstation; ids are never reused.
+    pub fn add_workstation(&mut self, capacity: f64) -> Result<usize, Error> {
+        if !positive(capacity) { return Err(Error::ZeroAmount); }
+        let id = self.next_workstation_id;
+        self.next_workstation_id += 1;
+        self.workstations.insert(id, Workstation { capacity, used: 0.0, total_used: 0.0, total_capacity: 0.0 });
+        self.journal.record(Event::AddWorkstation { id, capacity });
+        Ok(id)
+    }
+
+    pub fn set_workstation_capacity(&mut self, id: usize, capacity: f64) -> Result<(), Error> {
+        if !positive(capacity) { return Err(Error::ZeroAmount); }
+        self.workstations.get_mut(&id).ok_or(Error::NoSuchWorkstation)?.capacity = capacity;
+        self.journal.record(Event::SetWorkstationCapacity { id, capacity });
+        Ok(())
+    }
+
+    /// Makes manufacturing of the variant use the capacity of the workstation, `None` for unlimited.
+    pub fn assign_workstation(&mut self, product_id: usize, variant_id: usize, workstation_id: Option<usize>) -> Result<(), Error> {
+        if workstation_id.is_some_and(|id| !self.workstations.contains_key(&id)) { return Err(Error::NoSuchWorkstation); }
+        let product = self.products.get_mut(&product_id).ok_or(Error::NoSuchProduct)?;
+        product.variants.iter_mut().find(|v| v.id == variant_id).ok_or(Error::NoSuchVariant)?.workstation = workstation_id;
+        self.journal.record(Event::AssignWorkstation { product_id, variant_id, workstation_id });
+        Ok(())
+    }
+
+    pub fn utilisation_report(&self) -> Vec<Utilisation> {
+        self.workstations.iter().map(|(id, w)| w.utilisation(*id)).collect()
+    }
+
+    /// Orders in the order their due dates passed.
+    pub fn missed_deadlines(&self) -> &[MissedDeadline] {
+        &self.missed_deadlines
+    }
+
+    pub fn priority_levels(&self) -> usize {
+        self.production_queue.len()
+    }
+
+    /// Priorities of products go from 0, the most urgent, to `levels - 1`. Levels still
+    /// used by products or queued orders cannot be removed.
+    pub fn set_priority_levels(&mut self, levels: usize) -> Result<(), Error> {
+        if levels == 0 { return Err(Error::InvalidPriority); }
+        if self.products.values().any(|p| p.priority >= levels)
+            || self.production_queue.iter().skip(levels).any(|q| !q.is_empty()) {
+            return Err(Error::InUse);
+        }
+        self.production_queue.resize(levels, Vec::new());
+        if let Scheduling::WeightedFair(weights) = &mut self.scheduling {
+            weights.resize(levels, 1.0);
+        }
+        self.journal.record(Event::SetPriorityLevels { levels });
+        Ok(())
+    }
+
+    /// Weighted fair scheduling needs a positive weight for every level, aging a positive interval.
+    pub fn set_scheduling(&mut self, scheduling: Scheduling) -> Result<(), Error> {
+        match &scheduling {
+            Scheduling::Strict => {}
+            Scheduling::WeightedFair(weights) => {
+                if weights.len() != self.production_queue.len() { return Err(Error::InvalidPriority); }
+                if !weights.iter().all(|w| positive(*w)) { return Err(Error::ZeroAmount); }
+            }
+            Scheduling::Aging { interval } => if !positive(*interval) { return Err(Error::ZeroAmount); },
+        }
+        self.scheduling = scheduling.clone();
+        self.journal.record(Event::SetScheduling { scheduling });
+        Ok(())
+    }
+
+    pub fn set_preemption(&mut self, preemption: Preemption) {
+        self.reservations.preemption = preemption;
+        self.journal.record(Event::SetPreemption { preemption });
+    }
+
+    /// The reservations of an order, one per material.
+    pub fn reservations(&self, order_id: usize) -> Vec<Reservation> {
+        self.reservations.of_order(order_id).cloned().collect()
+    }
+
+    /// Manufacturing of variants without a duration of their own takes `work_complexity`
+    /// times this; zero, the default, makes it instantaneous.
+    pub fn set_time_per_complexity(&mut self, time: f64) -> Result<(), Error> {
+        if !non_negative(time) { return Err(Error::ZeroAmount); }
+        self.time_per_complexity = time;
+        self.journal.record(Event::SetTimePerComplexity { time });
+        Ok(())
+    }
+
+    /// Sets how long manufacturing the variant takes, `None` to derive it from its work complexity.
+    pub fn set_variant_duration(&mut self, product_id: usize, variant_id: usize, duration: Option<f64>) -> Result<(), Error> {
+        if duration.is_some_and(|d| !non_negative(d)) { return Err(Error::ZeroAmount); }
+        let product = self.products.get_mut(&product_id).ok_or(Error::NoSuchProduct)?;
+        product.variants.iter_mut().find(|v| v.id == variant_id).ok_or(Error::NoSuchVariant)?.duration = duration;
+        self.journal.record(Event::SetVariantDuration { product_id, variant_id, duration });
+        Ok(())
+    }
+
+    /// Returns the id of the new variant; ids are unique within the product and never reused.
+    pub fn add_product_variant(&mut self, product_id: usize, material_id: usize, material_amount: f64, work_complexity: f64) -> Result<usize, Error> {
+        if !positive(material_amount) || !non_negative(work_complexity) { return Err(Error::ZeroAmount); }
+        if !self.materials.contains_key(&material_id) { return Err(Error::NoSuchMaterial); }
+        let product = self.products.get_mut(&product_id).ok_or(Error::NoSuchProduct)?;
+        let variant_id = product.next_variant_id;
+        product.next_variant_id += 1;
+        product.variants.push(ProductVariant {
+            id: variant_id,
+            components: vec![Component {
+                kind: ComponentKind::Material,
+                id: material_id,
+                amount: material_amount,
+                scarcity_cache: 0.0,
+            }],
+            work_complexity,
+            retired: false,
+            duration: None,
+            workstation: None,
         });
+        self.journal.record(Event::AddProductVariant { product_id, id: variant_id, material_id, material_amount, work_complexity });
+        Ok(variant_id)
+    }
+
+    /// Adds another material to the bill of materials of an existing variant.
+    /// If the variant already consists of the material, the amounts are summed up.
+    pub fn add_variant_material(&mut self, product_id: usize, variant_id: usize, material_id: usize, material_amount: f64) -> Result<(), Error> {
+        if !positive(material_amount) { return Err(Error::ZeroAmount); }
+        if !self.products.contains_key(&product_id) { return Err(Error::NoSuchProduct); }
+        if !self.materials.contains_key(&material_id) { return Err(Error::NoSuchMaterial); }
+        self.add_variant_component(product_id, variant_id, ComponentKind::Material, material_id, material_amount)?;
+        self.journal.record(Event::AddVariantMaterial { product_id, variant_id, material_id, amount: material_amount });
+        Ok(())
+    }
+
+    /// Adds another product as a sub-assembly to the bill of materials of an existing variant.
+    /// Products that (even indirectly) consist of the variant's product cannot be added.
+    pub fn add_variant_product(&mut self, product_id: usize, variant_id: usize, component_id: usize, amount: f64) -> Result<(), Error> {
+        if !positive(amount) { return Err(Error::ZeroAmount); }
+        if !self.products.contains_key(&product_id) || !self.products.contains_key(&component_id) {
+            return Err(Error::NoSuchProduct);
+        }
+        self.add_variant_component(product_id, variant_id, ComponentKind::Product, component_id, amount)?;
+        self.journal.record(Event::AddVariantProduct { product_id, variant_id, component_id, amount });
+        Ok(())
+    }
+
+    fn add_variant_component(&mut self, product_id: usize, variant_id: usize, kind: ComponentKind, id: usize, amount: f64) -> Result<(), Error> {
+        if kind == ComponentKind::Product && internals::depends_on(&self.products, id, product_id) {
+            return Err(Error::CircularDependency);
+        }
+        let product = self.products.get_mut(&product_id).unwrap();
+        let variant = product.variants.iter_mut().find(|x| x.id == variant_id).ok_or(Error::NoSuchVariant)?;
+        match variant.components.iter_mut().find(|c| c.kind == kind && c.id == id) {
+            Some(c) => c.amount += amount,
+            None => variant.components.push(Component { kind, id, amount, scarcity_cache: 0.0 }),
+        }
+
+        // orders already waiting for this variant must account for the new component too
+        let added = ProductVariant {
+            id: variant_id,
+            components: vec![Component { kind, id, amount, scarcity_cache: 0.0 }],
+            work_complexity: 0.0,
+            retired: false,
+            duration: None,
+            workstation: None,
+        };
+        for order in self.production_queue.iter().flatten() {
+            if order.product_id == product_id && order.preferred_variant == variant_id {
+                added.add_demand(&mut self.products, &mut self.materials, order.product_amount);
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes a material. Variants made of it are removed as well if `cascade` is set,
+    /// otherwise a material still in use is kept.
+    pub fn remove_material(&mut self, id: usize, cascade: bool) -> Result<(), Error> {
+        if !self.materials.contains_key(&id) { return Err(Error::NoSuchMaterial); }
+        let users = internals::get_variants_using(&self.products, ComponentKind::Material, id);
+        if !users.is_empty() && !cascade { return Err(Error::InUse); }
+        for (product_id, variant_id) in users {
+            internals::remove_variant(self, product_id, variant_id);
+        }
+        self.materials.remove(&id);
+        self.journal.record(Event::RemoveMaterial { id, cascade });
+        Ok(())
+    }
+
+    /// Removes a product and cancels its queued orders. Variants of other products made of it
+    /// are removed as well if `cascade` is set, otherwise a product still in use is kept.
+    pub fn remove_product(&mut self, id: usize, cascade: bool) -> Result<(), Error> {
+        if !self.products.contains_key(&id) { return Err(Error::NoSuchProduct); }
+        if !cascade && !internals::get_variants_using(&self.products, ComponentKind::Product, id).is_empty() {
+            return Err(Error::InUse);
+        }
+        internals::remove_product(self, id);
+        self.journal.record(Event::RemoveProduct { id, cascade });
+        Ok(())
+    }
+
+    /// Removes a variant. Queued orders preferring it are moved to another variant
+    /// if their substitution policy allows it, otherwise they are cancelled.
+    pub fn remove_product_variant(&mut self, product_id: usize, variant_id: usize) -> Result<(), Error> {
+        let product = self.products.get(&product_id).ok_or(Error::NoSuchProduct)?;
+        if !product.variants.iter().any(|v| v.id == variant_id) { return Err(Error::NoSuchVariant); }
+        if product.variants.len() == 1 { return Err(Error::LastVariant); }
+        internals::remove_variant(self, product_id, variant_id);
+        self.journal.record(Event::RemoveProductVariant { product_id, variant_id });
+        Ok(())
+    }
+
+    pub fn retire_material(&mut self, id: usize) -> Result<(), Error> {
+        self.materials.get_mut(&id).ok_or(Error::NoSuchMaterial)?.retired = true;
+        self.journal.record(Event::RetireMaterial { id });
+        Ok(())
+    }
+
+    pub fn retire_product(&mut self, id: usize) -> Result<(), Error> {
+        self.products.get_mut(&id).ok_or(Error::NoSuchProduct)?.retired = true;
+        self.journal.record(Event::RetireProduct { id });
+        Ok(())
+    }
+
+    pub fn retire_product_variant(&mut self, product_id: usize, variant_id: usize) -> Result<(), Error> {
+        let product = self.products.get_mut(&product_id).ok_or(Error::NoSuchProduct)?;
+        product.variants.iter_mut().find(|v| v.id == variant_id).ok_or(Error::NoSuchVariant)?.retired = true;
+        self.journal.record(Event::RetireProductVariant { product_id, variant_id });
+        Ok(())
+    }
+
+    pub fn set_material_description(&mut self, id: usize, description: Option<&str>) -> Result<(), Error> {
+        self.materials.get_mut(&id).ok_or(Error::NoSuchMaterial)?.description = to_description(description)?;
+        self.journal.record(Event::SetMaterialDescription { id, description: description.map(String::from) });
+        Ok(())
+    }
+
+    pub fn set_material_unit(&mut self, id: usize, unit: Option<&str>) -> Result<(), Error> {
+        self.materials.get_mut(&id).ok_or(Error::NoSuchMaterial)?.unit = to_description(unit)?;
+        self.journal.record(Event::SetMaterialUnit { id, unit: unit.map(String::from) });
+        Ok(())
+    }
+
+    pub fn set_product_description(&mut self, id: usize, description: Option<&str>) -> Result<(), Error> {
+        self.products.get_mut(&id).ok_or(Error::NoSuchProduct)?.description = to_description(description)?;
+        self.journal.record(Event::SetProductDescription { id, description: description.map(String::from) });
+        Ok(())
     }
-    instance.materials.insert(variant.components.material_id, material);
-    products.insert(id, prod);
+}
 
-    if code > OK_QUEUE {
-        internals::process_queue(&mut instance.production_queue,
-                                 &mut instance.products,
-                                 &mut instance.materials,
-                                 &mut instance.finished_products,
-                                 instance.verbose);
+// The C functions below return 0 on success and `Error::code` otherwise,
+// order functions return `OrderOutcome` as a number instead of 0.
+// Unknown ids are reported, not panicked on; `ffi::guard` catches the rest.
+
+fn order_code(result: Result<(usize, OrderOutcome), Error>) -> u8 {
+    match result {
+        Ok((_, outcome)) => outcome as u8,
+        Err(e) => e.code(),
     }
+}
 
-    code
+const PANICKED: u8 = Error::Internal as u8;
+
+/// The id of the order is written to `order_id` even if the order gets rejected.
+#[no_mangle]
+pub extern "C" fn order_product(instance: &mut Instance,
+                            id: usize,
+                            amount: f64,
+                            variant_id: usize,
+                            user_id: usize,
+                            allow_substitution: bool,
+                            order_id: Option<&mut usize>) -> u8
+{
+    guard(PANICKED, || {
+        if let Some(order_id) = order_id { *order_id = instance.next_order_id; }
+        let substitution = if allow_substitution { Substitution::Any } else { Substitution::Never };
+        order_code(instance.order_product(id, amount, variant_id, user_id, substitution))
+    })
 }
 
+/// Like `order_product`, see `OrderOptions`.
 #[no_mangle]
-pub extern fn process_queue(instance: &mut Instance) {
-    internals::process_queue(&mut instance.production_queue,
-                             &mut instance.products,
-                             &mut instance.materials,
-                             &mut instance.finished_products,
-                             instance.verbose);
+pub extern "C" fn order_product_with(instance: &mut Instance,
+                                     id: usize,
+                                     amount: f64,
+                                     variant_id: usize,
+                                     user_id: usize,
+                                     allow_substitution: bool,
+                                     options: OrderOptions,
+                                     order_id: Option<&mut usize>) -> u8
+{
+    guard(PANICKED, || {
+        if let Some(order_id) = order_id { *order_id = instance.next_order_id; }
+        let substitution = if allow_substitution { Substitution::Any } else { Substitution::Never };
+        order_code(instance.order_product_with(id, amount, variant_id, user_id, substitution, options))
+    })
+}
+
+/// The id of the order is written to `order_id` even if the order gets rejected.
+#[no_mangle]
+pub extern "C" fn produce_to_stock(instance: &mut Instance,
+                                   id: usize,
+                                   amount: f64,
+                                   variant_id: usize,
+                                   allow_substitution: bool,
+                                   order_id: Option<&mut usize>) -> u8
+{
+    guard(PANICKED, || {
+        if let Some(order_id) = order_id { *order_id = instance.next_order_id; }
+        let substitution = if allow_substitution { Substitution::Any } else { Substitution::Never };
+        order_code(instance.produce_to_stock(id, amount, variant_id, substitution))
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn cancel_order(instance: &mut Instance, order_id: usize) -> u8 {
+    guard(PANICKED, || error::code(instance.cancel_order(order_id)))
+}
+
+#[no_mangle]
+pub extern "C" fn get_order_status(instance: &Instance, order_id: usize) -> COption<OrderStatus> {
+    guard(COption::none(), || instance.get_order_status(order_id).into())
+}
+
+#[no_mangle]
+pub extern "C" fn process_queue(instance: &mut Instance) {
+    guard((), || instance.process_queue())
 }
 
 //pub fn is_in_supply() {}
 
 #[no_mangle]
-pub extern fn update_supply(instance: &mut Instance, id: usize, amount: f64) -> bool {
-    let result = match instance.materials.get_mut(id) {
-        Some(x) => {
-            x.supply = amount;
-            true
+pub extern "C" fn update_supply(instance: &mut Instance, id: usize, amount: f64) -> u8 {
+    guard(PANICKED, || error::code(instance.update_supply(id, amount)))
+}
+
+/// The id of the new variant is written to `id`.
+#[no_mangle]
+pub extern "C" fn add_product_variant(instance: &mut Instance, product_id: usize, material_id: usize, material_amount: f64, work_complexity: f64, id: Option<&mut usize>) -> u8 {
+    guard(PANICKED, || {
+        let result = instance.add_product_variant(product_id, material_id, material_amount, work_complexity);
+        if let (Ok(variant_id), Some(id)) = (&result, id) { *id = *variant_id; }
+        error::code(result)
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn add_variant_material(instance: &mut Instance, product_id: usize, variant_id: usize, material_id: usize, material_amount: f64) -> u8 {
+    guard(PANICKED, || error::code(instance.add_variant_material(product_id, variant_id, material_id, material_amount)))
+}
+
+#[no_mangle]
+pub extern "C" fn add_variant_product(instance: &mut Instance, product_id: usize, variant_id: usize, component_id: usize, amount: f64) -> u8 {
+    guard(PANICKED, || error::code(instance.add_variant_product(product_id, variant_id, component_id, amount)))
+}
+
+#[no_mangle]
+pub extern "C" fn remove_material(instance: &mut Instance, id: usize, cascade: bool) -> u8 {
+    guard(PANICKED, || error::code(instance.remove_material(id, cascade)))
+}
+
+#[no_mangle]
+pub extern "C" fn remove_product(instance: &mut Instance, id: usize, cascade: bool) -> u8 {
+    guard(PANICKED, || error::code(instance.remove_product(id, cascade)))
+}
+
+#[no_mangle]
+pub extern "C" fn remove_product_variant(instance: &mut Instance, product_id: usize, variant_id: usize) -> u8 {
+    guard(PANICKED, || error::code(instance.remove_product_variant(product_id, variant_id)))
+}
+
+#[no_mangle]
+pub extern "C" fn retire_material(instance: &mut Instance, id: usize) -> u8 {
+    guard(PANICKED, || error::code(instance.retire_material(id)))
+}
+
+#[no_mangle]
+pub extern "C" fn retire_product(instance: &mut Instance, id: usize) -> u8 {
+    guard(PANICKED, || error::code(instance.retire_product(id)))
+}
+
+#[no_mangle]
+pub extern "C" fn retire_product_variant(instance: &mut Instance, product_id: usize, variant_id: usize) -> u8 {
+    guard(PANICKED, || error::code(instance.retire_product_variant(product_id, variant_id)))
+}
+
+#[no_mangle]
+pub extern "C" fn update_material_deposit_size(instance: &mut Instance, id: usize, size: f64) -> u8 {
+    guard(PANICKED, || error::code(instance.update_deposit_size(id, size)))
+}
+
+#[no_mangle]
+pub extern "C" fn set_material_regeneration(instance: &mut Instance, id: usize, rate: f64, capacity: f64) -> u8 {
+    guard(PANICKED, || error::code(instance.set_regeneration(id, Some(Regeneration { rate, capacity }))))
+}
+
+#[no_mangle]
+pub extern "C" fn clear_material_regeneration(instance: &mut Instance, id: usize) -> u8 {
+    guard(PANICKED, || error::code(instance.set_regeneration(id, None)))
+}
+
+#[no_mangle]
+pub extern "C" fn advance_time(instance: &mut Instance, duration: f64) -> u8 {
+    guard(PANICKED, || error::code(instance.advance_time(duration)))
+}
+
+#[no_mangle]
+pub extern "C" fn tick(instance: &mut Instance) {
+    guard((), || instance.tick())
+}
+
+#[no_mangle]
+pub extern "C" fn advance_to(instance: &mut Instance, time: f64) -> u8 {
+    guard(PANICKED, || error::code(instance.advance_to(time)))
+}
+
+#[no_mangle]
+pub extern "C" fn get_time(instance: &Instance) -> f64 {
+    instance.time
+}
+
+#[no_mangle]
+pub extern "C" fn set_time_per_complexity(instance: &mut Instance, time: f64) -> u8 {
+    guard(PANICKED, || error::code(instance.set_time_per_complexity(time)))
+}
+
+#[no_mangle]
+pub extern "C" fn set_variant_duration(instance: &mut Instance, product_id: usize, variant_id: usize, duration: f64) -> u8 {
+    guard(PANICKED, || error::code(instance.set_variant_duration(product_id, variant_id, Some(duration))))
+}
+
+#[no_mangle]
+pub extern "C" fn clear_variant_duration(instance: &mut Instance, product_id: usize, variant_id: usize) -> u8 {
+    guard(PANICKED, || error::code(instance.set_variant_duration(product_id, variant_id, None)))
+}
+
+/// `id` receives the id of the new workstation.
+#[no_mangle]
+pub extern "C" fn add_workstation(instance: &mut Instance, capacity: f64, id: Option<&mut usize>) -> u8 {
+    guard(PANICKED, || match instance.add_workstation(capacity) {
+        Ok(workstation_id) => {
+            if let Some(id) = id { *id = workstation_id; }
+            0
         }
-        None => false
-    };
-    internals::process_queue(&mut instance.production_queue,
-                             &mut instance.products,
-                             &mut instance.materials,
-                             &mut instance.finished_products,
-                             instance.verbose);
+        Err(e) => e.code(),
+    })
+}
 
-    result
+#[no_mangle]
+pub extern "C" fn set_workstation_capacity(instance: &mut Instance, id: usize, capacity: f64) -> u8 {
+    guard(PANICKED, || error::code(instance.set_workstation_capacity(id, capacity)))
 }
 
 #[no_mangle]
-pub extern fn add_product_variant(instance: &mut Instance, product_id: usize, material_id: usize, material_amount: f64, work_complexity: f64) -> u8 {
-    const NO_SUCH_PRODUCT: u8 = 1;
-    const NO_SUCH_MATERIAL: u8 = 2;
+pub extern "C" fn assign_workstation(instance: &mut Instance, product_id: usize, variant_id: usize, workstation_id: usize) -> u8 {
+    guard(PANICKED, || error::code(instance.assign_workstation(product_id, variant_id, Some(workstation_id))))
+}
 
-    if instance.products.len() <= product_id { return NO_SUCH_PRODUCT; }
-    if instance.materials.len() <= material_id { return NO_SUCH_MATERIAL; }
-    let product = instance.products.get_mut(product_id).unwrap();
-    let variant_id = product.variants.len(); //this must be changed when remove_product_variant is implemented!
-    product.variants.push(ProductVariant {
-        id: variant_id,
-        components: Component { material_id, material_amount, scarcity_cache: 0.0 },
-        work_complexity,
-    });
-    0
+#[no_mangle]
+pub extern "C" fn unassign_workstation(instance: &mut Instance, product_id: usize, variant_id: usize) -> u8 {
+    guard(PANICKED, || error::code(instance.assign_workstation(product_id, variant_id, None)))
 }
 
-//pub fn update_material_deposit_size() {}
+#[no_mangle]
+pub extern "C" fn get_workstation_utilisation(instance: &Instance, id: usize) -> COption<Utilisation> {
+    instance.workstations.get(&id).map(|w| w.utilisation(id)).into()
+}
+
+#[no_mangle]
+pub extern "C" fn get_in_production_count(instance: &Instance) -> usize {
+    instance.in_production.len()
+}
+
+#[no_mangle]
+pub extern "C" fn add_supply(instance: &mut Instance, id: usize, amount: f64) -> u8 {
+    guard(PANICKED, || error::code(instance.add_supply(id, amount)))
+}
+
+#[no_mangle]
+pub extern "C" fn remove_supply(instance: &mut Instance, id: usize, amount: f64) -> u8 {
+    guard(PANICKED, || error::code(instance.remove_supply(id, amount)))
+}
+
+#[no_mangle]
+pub extern "C" fn read_supply(instance: &mut Instance, id: usize, amount: f64, timestamp: f64) -> u8 {
+    guard(PANICKED, || error::code(instance.read_supply(id, amount, timestamp)))
+}
+
+/// The last reading of the material; nothing before the first one.
+#[no_mangle]
+pub extern "C" fn get_reconciliation(instance: &Instance, id: usize) -> COption<Reconciliation> {
+    instance.materials.get(&id).and_then(|m| m.reading).into()
+}
+
+/// `extracted` receives the amount actually moved to the supply.
+#[no_mangle]
+pub extern "C" fn extract_material(instance: &mut Instance, id: usize, amount: f64, extracted: Option<&mut f64>) -> u8 {
+    guard(PANICKED, || match instance.extract(id, amount) {
+        Ok(amount) => {
+            if let Some(extracted) = extracted { *extracted = amount; }
+            0
+        }
+        Err(e) => e.code(),
+    })
+}
 
 #[no_mangle]
-pub extern fn get_material_count(instance: &Instance) -> usize {
+pub extern "C" fn get_material_count(instance: &Instance) -> usize {
     instance.materials.len()
 }
 
 #[no_mangle]
-pub extern fn get_material_demand(instance: &Instance, id: usize) -> f64 {
-    instance.materials[id].demand
+pub extern "C" fn get_material_demand(instance: &Instance, id: usize) -> COption<f64> {
+    instance.materials.get(&id).map(|m| m.demand).into()
+}
+
+#[no_mangle]
+pub extern "C" fn get_material_supply(instance: &Instance, id: usize) -> COption<f64> {
+    instance.materials.get(&id).map(|m| m.supply).into()
+}
+
+#[no_mangle]
+pub extern "C" fn get_missed_deadline_count(instance: &Instance) -> usize {
+    instance.missed_deadlines.len()
+}
+
+#[no_mangle]
+pub extern "C" fn get_missed_deadline(instance: &Instance, index: usize) -> COption<MissedDeadline> {
+    instance.missed_deadlines.get(index).copied().into()
+}
+
+#[no_mangle]
+pub extern "C" fn get_priority_levels(instance: &Instance) -> usize {
+    instance.production_queue.len()
+}
+
+#[no_mangle]
+pub extern "C" fn set_priority_levels(instance: &mut Instance, levels: usize) -> u8 {
+    guard(PANICKED, || error::code(instance.set_priority_levels(levels)))
+}
+
+#[no_mangle]
+pub extern "C" fn set_strict_scheduling(instance: &mut Instance) -> u8 {
+    guard(PANICKED, || error::code(instance.set_scheduling(Scheduling::Strict)))
+}
+
+#[no_mangle]
+pub extern "C" fn set_aging_scheduling(instance: &mut Instance, interval: f64) -> u8 {
+    guard(PANICKED, || error::code(instance.set_scheduling(Scheduling::Aging { interval })))
 }
 
 #[no_mangle]
-pub extern fn get_material_supply(instance: &Instance, id: usize) -> f64 {
-    instance.materials[id].supply
+pub extern "C" fn get_material_reserved(instance: &Instance, id: usize) -> COption<f64> {
+    instance.materials.get(&id).map(|m| m.reserved).into()
 }
 
 #[no_mangle]
-pub extern fn get_material_scarcity(instance: &Instance, id: usize) -> f64 {
-    instance.materials.get(id).unwrap().scarcity_cache
+pub extern "C" fn get_available_to_promise(instance: &Instance, id: usize) -> COption<f64> {
+    instance.materials.get(&id).map(|m| m.available_to_promise()).into()
 }
 
 #[no_mangle]
-pub extern fn get_product_count(instance: &Instance) -> usize {
+pub extern "C" fn get_reservation(instance: &Instance, order_id: usize, material_id: usize) -> COption<Reservation> {
+    instance.reservations.get(order_id, material_id).cloned().into()
+}
+
+#[no_mangle]
+pub extern "C" fn set_preemption(instance: &mut Instance, preemption: Preemption) {
+    guard((), || instance.set_preemption(preemption))
+}
+
+#[no_mangle]
+pub extern "C" fn get_material_deposit_size(instance: &Instance, id: usize) -> COption<f64> {
+    instance.materials.get(&id).map(|m| m.deposit_size).into()
+}
+
+#[no_mangle]
+pub extern "C" fn get_material_consumed(instance: &Instance, id: usize) -> COption<f64> {
+    instance.materials.get(&id).map(|m| m.consumed).into()
+}
+
+#[no_mangle]
+pub extern "C" fn get_material_scarcity(instance: &Instance, id: usize) -> COption<f64> {
+    instance.materials.get(&id).map(|m| m.scarcity_cache).into()
+}
+
+#[no_mangle]
+pub extern "C" fn get_product_count(instance: &Instance) -> usize {
     instance.products.len()
 }
 
 #[no_mangle]
-pub extern fn get_product_supply(instance: &Instance, id: usize) -> f64 {
-    instance.products[id].supply
+pub extern "C" fn get_product_supply(instance: &Instance, id: usize) -> COption<f64> {
+    instance.products.get(&id).map(|p| p.supply).into()
+}
+
+#[no_mangle]
+pub extern "C" fn get_product_demand(instance: &Instance, id: usize) -> COption<f64> {
+    instance.products.get(&id).map(|p| p.demand).into()
 }
 
 #[no_mangle]
-pub extern fn get_product_demand(instance: &Instance, id: usize) -> f64 {
-    instance.products[id].demand
+pub extern "C" fn get_product_priority(instance: &Instance, id: usize) -> COption<usize> {
+    instance.products.get(&id).map(|p| p.priority).into()
+}
+
+fn find_variant(instance: &Instance, product_id: usize, variant_id: usize) -> Option<&ProductVariant> {
+    instance.products.get(&product_id)?.variants.iter().find(|v| v.id == variant_id)
 }
 
 #[no_mangle]
-pub extern fn get_product_priority(instance: &Instance, id: usize) -> usize {
-    instance.products[id].priority
+pub extern "C" fn get_variant_component_count(instance: &Instance, product_id: usize, variant_id: usize) -> COption<usize> {
+    find_variant(instance, product_id, variant_id).map(|v| v.components.len()).into()
 }
 
 #[no_mangle]
-pub extern fn get_product_variant(instance: &Instance, product_id: usize, variant_id: usize) -> Component {
-    instance.products[product_id].variants[variant_id].components
+pub extern "C" fn get_variant_component(instance: &Instance, product_id: usize, variant_id: usize, index: usize) -> COption<Component> {
+    find_variant(instance, product_id, variant_id).and_then(|v| v.components.get(index)).copied().into()
 }
 
+/// The first component of the variant.
+#[deprecated(since="0.2.0", note="please use `get_variant_component` instead")]
 #[no_mangle]
-pub extern fn get_next_finished(instance: &mut Instance) -> COption<Order> {
-    match instance.finished_products.pop() {
-        Some(p) => COption { is_some: true, data: p },
-        None => COption {
-            is_some: false,
-            data: Order {
-                product_id: 0,
-                product_amount: 0.0,
-                preferred_variant: 0,
-                user_id: 0,
-                allow_substitution: false,
-            },
-        }
-    }
+pub extern "C" fn get_product_variant(instance: &Instance, product_id: usize, variant_id: usize) -> COption<Component> {
+    get_variant_component(instance, product_id, variant_id, 0)
 }
 
 #[no_mangle]
-pub extern fn get_finished_count(instance: &Instance) -> usize {
+pub extern "C" fn get_next_finished(instance: &mut Instance) -> COption<FinishedOrder> {
+    guard(COption::none(), || instance.take_finished().into())
+}
+
+#[no_mangle]
+pub extern "C" fn get_finished_count(instance: &Instance) -> usize {
     instance.finished_products.len()
 }
 
 #[no_mangle]
-pub extern fn get_queue_len(instance: &Instance) -> usize {
+pub extern "C" fn get_queue_len(instance: &Instance) -> usize {
     let mut total = 0;
     for i in instance.production_queue.iter() {
         total += i.len()
@@ -380,27 +1910,31 @@ pub extern fn get_queue_len(instance: &Instance) -> usize {
     total
 }
 
-pub fn get_product_variants(instance: &Instance, id: usize) -> &Vec<ProductVariant> {
-    &instance.products[id].variants
+pub fn get_product_variants(instance: &Instance, id: usize) -> Option<&Vec<ProductVariant>> {
+    instance.products.get(&id).map(|p| &p.variants)
 }
 
 #[no_mangle]
-pub extern fn tst_set_product_supply(instance: &mut Instance, id: usize, count: f64) {
-    instance.products.get_mut(id).unwrap().supply = count;
+pub extern "C" fn tst_set_product_supply(instance: &mut Instance, id: usize, count: f64) -> u8 {
+    match instance.products.get_mut(&id) {
+        Some(p) => p.supply = count,
+        None => return Error::NoSuchProduct.code(),
+    }
+    0
 }
 
 #[deprecated(since="0.1.6", note="please use `self.get_material` instead")]
-pub fn tst_get_material(instance: &Instance, id: usize) -> Material {
-    instance.materials[id].clone()
+pub fn tst_get_material(instance: &Instance, id: usize) -> Option<Material> {
+    instance.materials.get(&id).cloned()
 }
 
 #[deprecated(since="0.1.6", note="please use `self.get_materials` instead")]
-pub fn tst_get_materials(instance: &Instance) -> &Vec<Material> {
+pub fn tst_get_materials(instance: &Instance) -> &BTreeMap<usize, Material> {
     &instance.materials
 }
 
 #[deprecated(since="0.1.6", note="please use `self.get_products` instead")]
-pub fn tst_get_products(instance: &Instance) -> &Vec<Product> {
+pub fn tst_get_products(instance: &Instance) -> &BTreeMap<usize, Product> {
     &instance.products
 }
 
@@ -411,37 +1945,50 @@ mod tests {
 
     #[test]
     fn add_same_material() {
-        let instance = &mut init();
-        add_material(instance, 8.);
-        add_material(instance, 1.);
-        assert_eq!(instance.materials.get(0).unwrap().supply, 8.);
+        let instance = &mut Instance::new();
+        instance.add_material("wood", 8.).unwrap();
+        assert_eq!(instance.add_material(" wood", 1.), Err(Error::DuplicateMaterial));
+        assert_eq!(instance.materials[&0].supply, 8.);
+        assert_eq!(instance.materials.len(), 1);
     }
 
     #[test]
     fn add_product_without_material() {
-        let instance = &mut init();
-        assert_ne!(!add_product(instance,12345, 10., 0, 1.0), 0);
+        let instance = &mut Instance::new();
+        assert_eq!(instance.add_product("chair", 12345, 10., 0, 1.0), Err(Error::NoSuchMaterial));
     }
 
     #[test]
     fn add_same_product() {
-        let instance = &mut init();
-        add_material(instance, 8.);
-        add_product(instance, 0, 10., 0, 1.0);
-        add_product(instance, 0, 5., 0, 1.0);
-        assert_eq!(instance.products.get(0).unwrap().variants.first().unwrap().components.material_amount, 10.);
+        let instance = &mut Instance::new();
+        instance.add_material("wood", 8.).unwrap();
+        instance.add_product("chair", 0, 10., 0, 1.0).unwrap();
+        assert_eq!(instance.add_product("chair", 0, 5., 0, 1.0), Err(Error::DuplicateProduct));
+        assert_eq!(instance.products[&0].variants.first().unwrap().components[0].amount, 10.);
     }
 
     #[test]
     fn add_prod_zero_mat() {
-        let instance = &mut init();
-        add_material(instance, 8.);
-        assert_ne!(add_product(instance, 0, 0., 0, 1.0), 0);
+        let instance = &mut Instance::new();
+        instance.add_material("wood", 8.).unwrap();
+        assert_eq!(instance.add_product("chair", 0, 0., 0, 1.0), Err(Error::ZeroAmount));
     }
 
     #[test]
     fn order_zero_products() {
         let instance = &mut init();
-        assert_eq!(order_product(instance, 0, 0., 0, 0, true), 2);
+        assert_eq!(order_product(instance, 0, 0., 0, 0, true, None), Error::ZeroAmount.code());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn order_missing_product() {
+        let instance = &mut init();
+        assert_eq!(order_product(instance, 0, 1., 0, 0, true, None), 13);
+    }
+
+    #[test]
+    fn guard_catches_panic() {
+        assert_eq!(guard(PANICKED, || panic!("bug")), 255);
+        assert_eq!(guard(PANICKED, || 0), 0);
+    }
+}
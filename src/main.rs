@@ -43,7 +43,8 @@ fn main() {
     let mut instance = init();
     instance.verbose = VERBOSITY_INNER;
     let verbose = instance.verbose;
-    let mut instance = &mut instance;
+    let instance = &mut instance;
+    if verbose >= VERBOSITY_INNER { instance.subscribe(print_notification); }
     let mut num: usize = 0;
     let mut f0_count: usize = 0;
     let mut f1_count: usize = 0;
@@ -67,22 +68,22 @@ fn main() {
 
     while num < cycles || cycles == 0 {
         fn_num = rng.gen::<u8>() % 10;
-        evgen = event_generator::run(&mut instance, fn_num, &mut rng, max_values);
+        evgen = event_generator::run(instance, fn_num, &mut rng, max_values);
         match fn_num {
             //add material
             0 => {
                 match evgen {
                     Ok(result) => {
                         if cfg!(feature = "cz") {
-                            println!("[{}] Přidávám materiál #{} do databáze; množství: {}",
-                                     num, result.primary_id, result.amount);
+                            println!("[{}] Přidávám materiál #{} \"{}\" do databáze; množství: {}",
+                                     num, result.primary_id, instance.get_material(result.primary_id).unwrap().name(), result.amount);
                         } else {
-                            println!("[{}] Adding material #{} to the database, supply: {}",
-                                     num, result.primary_id, result.amount);
+                            println!("[{}] Adding material #{} \"{}\" to the database, supply: {}",
+                                     num, result.primary_id, instance.get_material(result.primary_id).unwrap().name(), result.amount);
                         }
                         f0_count += 1;
                     }
-                    Err(1) => {
+                    Err(Error::InvalidName) => {
                         if cfg!(feature = "cz") {
                             println!("[{}] Přidání materiálu selhalo. \
                             Název nesmí být prázdný nebo obsahovat netiskuté znaky.", num);
@@ -91,7 +92,7 @@ fn main() {
                             Name cannot be empty or contain only white spaces.", num);
                         }
                     }
-                    Err(2) => {
+                    Err(Error::ZeroAmount) => {
                         if verbose >= 3 {
                             if cfg!(feature = "cz") {
                                 println!("[{}] Přidání materiálu selhalo. \
@@ -102,7 +103,7 @@ fn main() {
                             }
                         }
                     }
-                    Err(3) => {
+                    Err(Error::DuplicateMaterial) => {
                         if verbose >= 3 {
                             if cfg!(feature = "cz") {
                                 println!("[{}] Přidání materiálu selhalo. \
@@ -129,15 +130,15 @@ fn main() {
                 match evgen {
                     Ok(result) => {
                         if cfg!(feature = "cz") {
-                            println!("[{}] Přidávám produkt #{} složen z {} kusů materiálu #{} \
-                        do databáze", num, result.primary_id, result.amount, result.secondary_id);
+                            println!("[{}] Přidávám produkt #{} \"{}\" složen z {} kusů materiálu #{} \
+                        do databáze", num, result.primary_id, instance.get_product(result.primary_id).unwrap().name(), result.amount, result.secondary_id);
                         } else {
-                            println!("[{}] Adding product #{} composed of {}x material #{} \
-                        to the database", num, result.primary_id, result.amount, result.secondary_id);
+                            println!("[{}] Adding product #{} \"{}\" composed of {}x material #{} \
+                        to the database", num, result.primary_id, instance.get_product(result.primary_id).unwrap().name(), result.amount, result.secondary_id);
                         }
                         f1_count += 1;
                     }
-                    Err(1) => {
+                    Err(Error::InvalidName) => {
                         if verbose >= 3 {
                             if cfg!(feature = "cz") {
                                 println!("[{}] Přidání produktu selhalo. \
@@ -148,18 +149,7 @@ fn main() {
                             }
                         }
                     }
-                    Err(2) => {
-                        if verbose >= 3 {
-                            if cfg!(feature = "cz") {
-                                println!("[{}] Přidání produktu selhalo. \
-                        Název materiálu nesmí být prázdný nebo obsahovat netiskuté znaky.", num);
-                            } else {
-                                println!("[{}] Adding product failed. \
-                        Material name cannot be empty or contain only white spaces.", num);
-                            }
-                        }
-                    }
-                    Err(3) => {
+                    Err(Error::ZeroAmount) => {
                         if verbose >= 3 {
                             if cfg!(feature = "cz") {
                                 println!("[{}] Přidání produktu selhalo. \
@@ -170,7 +160,7 @@ fn main() {
                             }
                         }
                     }
-                    Err(4) => {
+                    Err(Error::NoSuchMaterial) => {
                         if cfg!(feature = "cz") {
                             println!("[{}] Přidání produktu selhalo. \
                         Materiál neexistuje.", num);
@@ -179,7 +169,7 @@ fn main() {
                         Material does not exist.", num);
                         }
                     }
-                    Err(5) => {
+                    Err(Error::DuplicateProduct) => {
                         if verbose >= 3 {
                             if cfg!(feature = "cz") {
                                 println!("[{}] Přidání produktu selhalo. \
@@ -198,52 +188,59 @@ fn main() {
                 }
             }
             // order product
-            2 | 3 | 4 | 5 => {
+            2..=5 => {
                 match evgen {
                     Ok(result) => {
-                        match *result.code {
-                            4 => {
+                        match result.outcome {
+                            OrderOutcome::Delivered => {
+                                if cfg!(feature = "cz") {
+                                    println!("[{}] Vydávám {} produktů #{} ze skladu.", num, result.amount, result.primary_id);
+                                } else {
+                                    println!("[{}] Delivering {}x product #{} from stock.", num, result.amount, result.primary_id);
+                                }
+                            }
+                            OrderOutcome::NotAvailable => {
                                 if cfg!(feature = "cz")
                                 {
                                     println!("[{}] Výroba {} produktů #{} ZAMÍTNUTA. \
                         Materiál #{} není k dispozici; nedostatkovost: {:.2}", num, result.amount, result.primary_id, result.secondary_id,
-                                             get_material_scarcity(instance, result.secondary_id));
+                                             instance.get_material(result.secondary_id).unwrap().scarcity_cache);
                                 } else {
                                     println!("[{}] Manufacturing of {}x product #{} DENIED. \
                         Material #{} not available; scarcity: {:.2}", num, result.amount, result.primary_id, result.secondary_id,
-                                             get_material_scarcity(instance, result.secondary_id));
+                                             instance.get_material(result.secondary_id).unwrap().scarcity_cache);
                                 }
                                 failed_no_supply += 1;
                             }
-                            5 => {
+                            OrderOutcome::Scarce => {
                                 if cfg!(feature = "cz") {
                                     println!("[{}] Výroba {} produktů #{} ZAMÍTNUTA. \
                         Materiál #{} nedostatkový: {:.2} > 50.", num, result.amount, result.primary_id, result.secondary_id,
-                                             get_material_scarcity(instance, result.secondary_id));
+                                             instance.get_material(result.secondary_id).unwrap().scarcity_cache);
                                 } else {
                                     println!("[{}] Manufacturing of {}x product #{} DENIED. \
                         Material #{} scarce: {:.2} > 50.", num, result.amount, result.primary_id, result.secondary_id,
-                                             get_material_scarcity(instance, result.secondary_id));
+                                             instance.get_material(result.secondary_id).unwrap().scarcity_cache);
                                 }
                                 failed_scarce += 1;
                             }
-                            _ => {
+                            OrderOutcome::Queued => {
                                 if cfg!(feature = "cz") {
                                     println!("[{}] Objednávám produkt #{} \
                         za cenu {} kusů materiálu #{}, nedostatkovost: {:.2}",
                                              num, result.primary_id, result.amount, result.secondary_id,
-                                             get_material_scarcity(instance, result.secondary_id))
+                                             instance.get_material(result.secondary_id).unwrap().scarcity_cache)
                                 } else {
                                     println!("[{}] Ordering product #{} \
                         at the cost of {}x material #{}; scarcity: {:.2}",
                                              num, result.primary_id, result.amount, result.secondary_id,
-                                             get_material_scarcity(instance, result.secondary_id))
+                                             instance.get_material(result.secondary_id).unwrap().scarcity_cache)
                                 }
                             }
                         }
                         f2_count += 1;
                     }
-                    Err(2) => {
+                    Err(Error::ZeroAmount) => {
                         if verbose >= 3 {
                             if cfg!(feature = "cz") {
                                 println!("[{}] Výroba produktu selhala. \
@@ -254,7 +251,7 @@ fn main() {
                             }
                         }
                     }
-                    Err(3) => {
+                    Err(Error::NoSuchMaterial) => {
                         if verbose >= 3 {
                             if cfg!(feature = "cz") {
                                 println!("[{}] Výroba produktu selhala. \
@@ -265,27 +262,7 @@ fn main() {
                             }
                         }
                     }
-                    Err(4) => {
-                        if cfg!(feature = "cz") {
-                            println!("[{}] Výroba produktu selhala. \
-                        Materiál neí k dispozici", num);
-                        } else {
-                            println!("[{}] Manufacturing product failed. \
-                        Material not available.", num);
-                        } //safeguard for future code changes
-                        panic!("Material not available.");
-                    }
-                    Err(5) => {
-                        if cfg!(feature = "cz") {
-                            println!("[{}] Výroba produktu selhala. \
-                        Materiál je vzácný.", num);
-                        } else {
-                            println!("[{}] Manufacturing product failed. \
-                        Material scarce.", num); //safeguard for future code changes
-                        }
-                        panic!("Material scarce.");
-                    }
-                    Err(6) => {
+                    Err(Error::NoSuchProduct) => {
                         if verbose >= 3 {
                             if cfg!(feature = "cz") {
                                 println!("[{}] Výroba produktu selhala. \
@@ -333,19 +310,19 @@ fn main() {
                         if cfg!(feature = "cz") {
                             println!("[{}] Aktualizuji nabídku materiálu #{} na {} ks; \
                         poptávka: {}, nedostatkovost: {:.2}", num, result.primary_id, result.amount,
-                                     get_material_demand(instance, result.primary_id),
-                                     instance.get_material(result.primary_id).get_scarcity()
+                                     instance.get_material(result.primary_id).unwrap().demand,
+                                     instance.get_material(result.primary_id).unwrap().get_scarcity()
                             );
                         } else {
                             println!("[{}] Updating supply of material #{} to {}; \
                         demand: {}, scarcity: {:.2}", num, result.primary_id, result.amount,
-                                     get_material_demand(instance, result.primary_id),
-                                     instance.get_material(result.primary_id).get_scarcity()
+                                     instance.get_material(result.primary_id).unwrap().demand,
+                                     instance.get_material(result.primary_id).unwrap().get_scarcity()
                             );
                         }
                         f3_count += 1;
                     }
-                    Err(1) => {
+                    Err(Error::NoSuchMaterial) => {
                         if cfg!(feature = "cz") {
                             println!("[{}] Aktualizace nabídky materiálu selhala. \
                         Databáze materiálů je prázdná.", num);
@@ -354,15 +331,6 @@ fn main() {
                         No materials in database.", num);
                         }
                     }
-                    Err(2) => {
-                        if cfg!(feature = "cz") {
-                            println!("[{}] Aktualizace nabídky materiálu selhala. \
-                        Proces selhal.", num);
-                        } else {
-                            println!("[{}] Updating supply of material failed. \
-                        Supply update failed.", num);
-                        }
-                    }
                     Err(_) => {
                         if cfg!(feature = "cz") {
                             println!("[{}] Aktualizace nabídky materiálu selhala. \
@@ -394,4 +362,52 @@ fn main() {
     }
     #[cfg(target_family = "windows")]
         std::io::stdin().read(&mut [0u8]).unwrap();
-}
\ No newline at end of file
+}
+
+fn print_notification(notification: &Notification) {
+    match *notification {
+        Notification::OrderQueued { product_id, amount, for_stock: true, .. } => {
+            if cfg!(feature = "cz") {
+                println!(" * Plánuji výrobu {}x produktu #{} na sklad.", amount, product_id);
+            } else {
+                println!(" * Scheduling production of {}x product #{} to stock.", amount, product_id);
+            }
+        }
+        Notification::VariantSelected { product_id, variant_id, preferred_variant, .. } if variant_id != preferred_variant => {
+            if cfg!(feature = "cz") {
+                println!(" * Nahrazuji variantu #{} produktu #{} efektivnější variantou #{}.", preferred_variant, product_id, variant_id);
+            } else {
+                println!(" * Substituting variant #{} of product #{} with the more efficient variant #{}.", preferred_variant, product_id, variant_id);
+            }
+        }
+        Notification::ProductManufactured { product_id, variant_id, amount, .. } => {
+            if cfg!(feature = "cz") {
+                println!(" * Vyrábím {}x produkt #{}, varianta #{}.", amount, product_id, variant_id);
+            } else {
+                println!(" * Manufacturing {}x product #{}, variant #{}.", amount, product_id, variant_id);
+            }
+        }
+        Notification::MaterialScarce { material_id, scarcity } => {
+            if cfg!(feature = "cz") {
+                println!(" * Materiál #{} je nedostatkový: {:.2}.", material_id, scarcity);
+            } else {
+                println!(" * Material #{} became scarce: {:.2}.", material_id, scarcity);
+            }
+        }
+        Notification::MaterialDepleting { material_id, remaining } => {
+            if cfg!(feature = "cz") {
+                println!(" * Materiál #{} dochází, zbývá {:.2}.", material_id, remaining);
+            } else {
+                println!(" * Material #{} is running out, {:.2} left.", material_id, remaining);
+            }
+        }
+        Notification::DeadlineMissed { order_id, due, .. } => {
+            if cfg!(feature = "cz") {
+                println!(" * Objednávka #{} nebyla hotová do {:.2}.", order_id, due);
+            } else {
+                println!(" * Order #{} was not finished by {:.2}.", order_id, due);
+            }
+        }
+        _ => {}
+    }
+}
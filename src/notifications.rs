@@ -0,0 +1,98 @@
+/*
+* Copyright 2017-2019 Michal Mauser
+*
+* This program is free software: you can redistribute it and/or modify
+* it under the terms of the GNU Affero General Public License as published by
+* the Free Software Foundation, either version 3 of the License, or
+* (at your option) any later version.
+*
+* This program is distributed in the hope that it will be useful,
+* but WITHOUT ANY WARRANTY; without even the implied warranty of
+* MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+* GNU Affero General Public License for more details.
+*
+* You should have received a copy of the GNU Affero General Public License
+* along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Notifications about what is happening inside the library, for hosts to react to.
+
+use std::os::raw::c_void;
+
+#[repr(C)]
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Notification {
+    /// An order, including sub-orders to stock, was put into the production queue.
+    OrderQueued { order_id: usize, product_id: usize, amount: f64, priority: usize, for_stock: bool },
+    /// The variant of the product the order is going to be manufactured from.
+    VariantSelected { order_id: usize, product_id: usize, variant_id: usize, preferred_variant: usize },
+    ProductManufactured { order_id: usize, product_id: usize, variant_id: usize, amount: f64 },
+    /// Manufactured or delivered from stock, possibly in parts.
+    OrderFinished { order_id: usize, product_id: usize, amount: f64, user_id: usize },
+    /// Demand for the material has just outgrown the equilibrium with its supply.
+    MaterialScarce { material_id: usize, scarcity: f64 },
+    /// Supply and deposit of the material have fallen below `DEPLETION_WARNING` of what it ever had.
+    MaterialDepleting { material_id: usize, remaining: f64 },
+    /// The order was not finished by its due date.
+    DeadlineMissed { order_id: usize, product_id: usize, due: f64 },
+}
+
+pub trait Subscriber: Send {
+    fn notify(&mut self, notification: &Notification);
+}
+
+impl<F: FnMut(&Notification) + Send> Subscriber for F {
+    fn notify(&mut self, notification: &Notification) {
+        self(notification)
+    }
+}
+
+/// C subscriber; `user_data` is handed back untouched.
+pub type Callback = extern "C" fn(notification: &Notification, user_data: *mut c_void);
+
+pub(crate) struct CSubscriber {
+    pub(crate) callback: Callback,
+    pub(crate) user_data: *mut c_void,
+}
+
+// the C host is responsible for the user data being usable from where the instance is
+unsafe impl Send for CSubscriber {}
+
+impl Subscriber for CSubscriber {
+    fn notify(&mut self, notification: &Notification) {
+        (self.callback)(notification, self.user_data)
+    }
+}
+
+/// Copies of an instance start without subscribers.
+#[derive(Default)]
+pub(crate) struct Subscribers {
+    list: Vec<(usize, Box<dyn Subscriber>)>,
+    next_id: usize,
+}
+
+impl Clone for Subscribers {
+    fn clone(&self) -> Self {
+        Subscribers::default()
+    }
+}
+
+impl Subscribers {
+    pub(crate) fn add(&mut self, subscriber: Box<dyn Subscriber>) -> usize {
+        self.next_id += 1;
+        self.list.push((self.next_id - 1, subscriber));
+        self.next_id - 1
+    }
+
+    pub(crate) fn remove(&mut self, id: usize) -> bool {
+        let len = self.list.len();
+        self.list.retain(|(i, _)| *i != id);
+        self.list.len() != len
+    }
+
+    pub(crate) fn notify(&mut self, notification: Notification) {
+        for (_, subscriber) in self.list.iter_mut() {
+            subscriber.notify(&notification);
+        }
+    }
+}
@@ -0,0 +1,254 @@
+/*
+* Copyright 2017-2019 Michal Mauser
+*
+* This program is free software: you can redistribute it and/or modify
+* it under the terms of the GNU Affero General Public License as published by
+* the Free Software Foundation, either version 3 of the License, or
+* (at your option) any later version.
+*
+* This program is distributed in the hope that it will be useful,
+* but WITHOUT ANY WARRANTY; without even the implied warranty of
+* MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+* GNU Affero General Public License for more details.
+*
+* You should have received a copy of the GNU Affero General Public License
+* along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Saving and loading of the complete instance.
+//!
+//! Both formats store a `Saved` envelope: the format version followed by the instance.
+//! In JSON it is an object with the `version` and `instance` keys; the instance holds
+//! `materials` and `products` as objects keyed by their ids, the `production_queue`
+//! as one array of orders per priority level, `finished_products`, `order_states` keyed
+//! by order id and the id counters. The binary form is the same data encoded by bincode,
+//! with the version as the leading little endian `u32`.
+//!
+//! Files of older versions are converted when loaded; newer ones are rejected. The versions:
+//!
+//! 1. The original layout with four priority levels.
+//! 2. Adds scarcity models, deposits, renewable materials, supply readings, the clock with
+//!    orders in production, workstations, reservations, scheduling and any number of levels.
+//! 3. Adds the priority and due date of orders and the missed deadlines.
+//! 4. Adds the parent of sub-orders.
+
+mod legacy;
+
+use bincode::Options;
+use crate::{ComponentKind, Error, Instance, OrderState, Products, Scheduling};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+
+/// Version written by `save`. Raised with every change of the saved layout, keeping the
+/// old binary layout in `legacy` and a file of it in tests/data.
+pub const FORMAT_VERSION: u32 = 4;
+
+#[repr(C)]
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Format {
+    Json,
+    /// Compact, but not meant to be read by people.
+    Binary,
+}
+
+/// Scarcity caches become infinite once a material is used up, JSON has no number for that.
+pub(crate) mod float {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Readable {
+        Number(f64),
+        Named(String),
+    }
+
+    pub fn serialize<S: Serializer>(value: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+        if value.is_finite() || !serializer.is_human_readable() { return serializer.serialize_f64(*value); }
+        serializer.serialize_str(&value.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+        if !deserializer.is_human_readable() { return f64::deserialize(deserializer); }
+        match Readable::deserialize(deserializer)? {
+            Readable::Number(value) => Ok(value),
+            Readable::Named(name) => name.parse().map_err(D::Error::custom),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Saved<'a> {
+    version: u32,
+    instance: &'a Instance,
+}
+
+pub(crate) fn save<W: Write>(instance: &Instance, writer: W, format: Format) -> Result<(), Error> {
+    let saved = Saved { version: FORMAT_VERSION, instance };
+    match format {
+        Format::Json => serde_json::to_writer_pretty(writer, &saved).map_err(|_| Error::Io),
+        Format::Binary => bincode::serialize_into(writer, &saved).map_err(|_| Error::Io),
+    }
+}
+
+pub(crate) fn load<R: Read>(mut reader: R, format: Format) -> Result<Instance, Error> {
+    let instance = match format {
+        Format::Json => {
+            // older versions lack only fields with defaults
+            let mut saved: serde_json::Value = serde_json::from_reader(reader).map_err(|_| Error::InvalidFormat)?;
+            check_version(saved.get("version").and_then(|v| v.as_u64()).ok_or(Error::InvalidFormat)?)?;
+            serde_json::from_value(saved["instance"].take()).map_err(|_| Error::InvalidFormat)?
+        }
+        Format::Binary => {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).map_err(|_| Error::Io)?;
+            if bytes.len() < 4 { return Err(Error::InvalidFormat); }
+            let (version, data) = bytes.split_at(4);
+            let version = u32::from_le_bytes([version[0], version[1], version[2], version[3]]);
+            check_version(u64::from(version))?;
+            match version {
+                1 => upgrade::<legacy::v1::Instance>(data)?,
+                2 => upgrade::<legacy::v2::Instance>(data)?,
+                3 => upgrade::<legacy::v3::Instance>(data)?,
+                _ => decode(data)?,
+            }
+        }
+    };
+    validate(&instance)?;
+    Ok(instance)
+}
+
+fn check_version(version: u64) -> Result<(), Error> {
+    if version == 0 || version > u64::from(FORMAT_VERSION) { return Err(Error::UnsupportedVersion); }
+    Ok(())
+}
+
+// data that does not fill the layout exactly was written in another one
+fn decode<T: DeserializeOwned>(data: &[u8]) -> Result<T, Error> {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .reject_trailing_bytes()
+        .deserialize(data)
+        .map_err(|_| Error::InvalidFormat)
+}
+
+// binary data of an older version gets the fields added since the same way its JSON does
+fn upgrade<T: DeserializeOwned + Serialize>(data: &[u8]) -> Result<Instance, Error> {
+    let old: T = decode(data)?;
+    let json = serde_json::to_value(old).map_err(|_| Error::InvalidFormat)?;
+    serde_json::from_value(json).map_err(|_| Error::InvalidFormat)
+}
+
+/// Checks that everything the instance refers to exists, so that a damaged or
+/// hand-edited file cannot make the library panic later.
+fn validate(instance: &Instance) -> Result<(), Error> {
+    let products = &instance.products;
+    let materials = &instance.materials;
+    if materials.keys().any(|id| *id >= instance.next_material_id)
+        || products.keys().any(|id| *id >= instance.next_product_id) {
+        return Err(Error::InvalidReference);
+    }
+    for product in products.values() {
+        if product.variants.is_empty() || product.priority >= instance.production_queue.len() { return Err(Error::InvalidReference); }
+        for (i, variant) in product.variants.iter().enumerate() {
+            if variant.id >= product.next_variant_id
+                || product.variants[..i].iter().any(|v| v.id == variant.id) {
+                return Err(Error::InvalidReference);
+            }
+            let missing = variant.components.iter().any(|c| match c.kind {
+                ComponentKind::Material => !materials.contains_key(&c.id),
+                ComponentKind::Product => !products.contains_key(&c.id),
+            });
+            if missing || variant.workstation.is_some_and(|id| !instance.workstations.contains_key(&id)) {
+                return Err(Error::InvalidReference);
+            }
+        }
+    }
+    for (i, material) in materials.values().enumerate() {
+        if materials.values().take(i).any(|m| m.name == material.name) { return Err(Error::DuplicateMaterial); }
+    }
+    for (i, product) in products.values().enumerate() {
+        if products.values().take(i).any(|p| p.name == product.name) { return Err(Error::DuplicateProduct); }
+    }
+    if has_cycle(products) { return Err(Error::CircularDependency); }
+
+    for order in instance.production_queue.iter().flatten() {
+        let valid = order.id < instance.next_order_id
+            && order.parent.is_none_or(|id| id < instance.next_order_id)
+            && products.get(&order.product_id).is_some_and(|p| p.variants.iter().any(|v| v.id == order.preferred_variant))
+            && matches!(instance.order_states.get(&order.id), Some(OrderState::Queued) | Some(OrderState::InProduction));
+        if !valid { return Err(Error::InvalidReference); }
+    }
+    for production in instance.in_production.iter() {
+        let valid = production.order.id < instance.next_order_id
+            && products.contains_key(&production.order.product_id)
+            && matches!(instance.order_states.get(&production.order.id), Some(OrderState::Queued) | Some(OrderState::InProduction));
+        if !valid { return Err(Error::InvalidReference); }
+    }
+    if instance.production_queue.is_empty() { return Err(Error::InvalidReference); }
+    if let Scheduling::WeightedFair(weights) = &instance.scheduling {
+        if weights.len() != instance.production_queue.len() { return Err(Error::InvalidReference); }
+    }
+    for reservation in instance.reservations.iter() {
+        if reservation.order_id >= instance.next_order_id
+            || (reservation.reserved > 0.0 && !materials.contains_key(&reservation.material_id)) {
+            return Err(Error::InvalidReference);
+        }
+    }
+    if instance.order_states.keys().any(|id| *id >= instance.next_order_id)
+        || instance.missed_deadlines.iter().any(|m| m.order_id >= instance.next_order_id)
+        || instance.workstations.keys().any(|id| *id >= instance.next_workstation_id) {
+        return Err(Error::InvalidReference);
+    }
+    Ok(())
+}
+
+fn has_cycle(products: &Products) -> bool {
+    // false while the product is being visited, true once it is known not to be in a cycle
+    fn visit(products: &Products, id: usize, visited: &mut HashMap<usize, bool>) -> bool {
+        match visited.get(&id) {
+            Some(done) => return !done,
+            None => visited.insert(id, false),
+        };
+        let cycle = products[&id].variants.iter().flat_map(|v| v.components.iter())
+            .any(|c| c.kind == ComponentKind::Product && visit(products, c.id, visited));
+        visited.insert(id, true);
+        cycle
+    }
+
+    let mut visited = HashMap::new();
+    products.keys().any(|id| visit(products, *id, &mut visited))
+}
+
+/// Names are kept as C strings but saved as plain strings.
+pub(crate) mod c_string {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use std::ffi::CString;
+
+    pub fn serialize<S: Serializer>(string: &CString, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(string.to_str().unwrap())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<CString, D::Error> {
+        CString::new(String::deserialize(deserializer)?).map_err(D::Error::custom)
+    }
+}
+
+pub(crate) mod optional_c_string {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use std::ffi::CString;
+
+    pub fn serialize<S: Serializer>(string: &Option<CString>, serializer: S) -> Result<S::Ok, S::Error> {
+        match string {
+            Some(s) => serializer.serialize_some(s.to_str().unwrap()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<CString>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|s| CString::new(s).map_err(D::Error::custom))
+            .transpose()
+    }
+}
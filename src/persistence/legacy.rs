@@ -0,0 +1,280 @@
+/*
+* Copyright 2017-2019 Michal Mauser
+*
+* This program is free software: you can redistribute it and/or modify
+* it under the terms of the GNU Affero General Public License as published by
+* the Free Software Foundation, either version 3 of the License, or
+* (at your option) any later version.
+*
+* This program is distributed in the hope that it will be useful,
+* but WITHOUT ANY WARRANTY; without even the implied warranty of
+* MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+* GNU Affero General Public License for more details.
+*
+* You should have received a copy of the GNU Affero General Public License
+* along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Binary layouts of older format versions. Bincode stores no field names, so old
+//! files are decoded into these exactly as they were written; the field names are the
+//! current ones, which lets them be converted like JSON of the same version.
+
+/// Version 1: four priority levels and no clock.
+pub(super) mod v1 {
+    use crate::{Component, OrderState, Substitution};
+    use serde::{Deserialize, Serialize};
+    use std::collections::{BTreeMap, HashMap};
+
+    #[derive(Serialize, Deserialize)]
+    pub(in crate::persistence) struct Instance {
+        materials: BTreeMap<usize, Material>,
+        products: BTreeMap<usize, Product>,
+        next_material_id: usize,
+        next_product_id: usize,
+        production_queue: [Vec<Order>; 4],
+        finished_products: Vec<Order>,
+        order_states: HashMap<usize, OrderState>,
+        next_order_id: usize,
+        verbose: usize,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Material {
+        name: String,
+        description: Option<String>,
+        unit: Option<String>,
+        #[serde(with = "crate::persistence::float")]
+        scarcity_cache: f64,
+        demand: f64,
+        supply: f64,
+        retired: bool,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Product {
+        name: String,
+        description: Option<String>,
+        variants: Vec<ProductVariant>,
+        supply: f64,
+        demand: f64,
+        priority: usize,
+        retired: bool,
+        next_variant_id: usize,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct ProductVariant {
+        id: usize,
+        components: Vec<Component>,
+        work_complexity: f64,
+        retired: bool,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Order {
+        id: usize,
+        product_id: usize,
+        product_amount: f64,
+        preferred_variant: usize,
+        user_id: usize,
+        substitution: Substitution,
+        for_stock: bool,
+    }
+}
+
+/// Version 2: orders had no priority override or due date.
+pub(super) mod v2 {
+    use crate::reservations::Preemption;
+    use crate::{Component, OrderState, Reconciliation, Regeneration, Substitution};
+    use serde::{Deserialize, Serialize};
+    use std::collections::{BTreeMap, HashMap};
+
+    #[derive(Serialize, Deserialize)]
+    pub(in crate::persistence) struct Instance {
+        materials: BTreeMap<usize, Material>,
+        products: BTreeMap<usize, Product>,
+        next_material_id: usize,
+        next_product_id: usize,
+        production_queue: Vec<Vec<Order>>,
+        scheduling: Scheduling,
+        finished_products: Vec<Order>,
+        order_states: HashMap<usize, OrderState>,
+        next_order_id: usize,
+        in_production: Vec<Production>,
+        time: f64,
+        time_per_complexity: f64,
+        workstations: BTreeMap<usize, Workstation>,
+        next_workstation_id: usize,
+        reservations: Reservations,
+        verbose: usize,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub(super) struct Material {
+        name: String,
+        description: Option<String>,
+        unit: Option<String>,
+        #[serde(with = "crate::persistence::float")]
+        scarcity_cache: f64,
+        demand: f64,
+        supply: f64,
+        reserved: f64,
+        deposit_size: f64,
+        consumed: f64,
+        regeneration: Option<Regeneration>,
+        harvest_rate: f64,
+        consumed_at_advance: f64,
+        reading: Option<Reconciliation>,
+        consumed_at_reading: f64,
+        changed_at: Option<f64>,
+        retired: bool,
+        scarce: bool,
+        depleting: bool,
+        scarcity_model: Option<String>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub(super) struct Product {
+        name: String,
+        description: Option<String>,
+        variants: Vec<ProductVariant>,
+        supply: f64,
+        demand: f64,
+        priority: usize,
+        retired: bool,
+        next_variant_id: usize,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct ProductVariant {
+        id: usize,
+        components: Vec<Component>,
+        work_complexity: f64,
+        retired: bool,
+        duration: Option<f64>,
+        workstation: Option<usize>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub(super) struct Workstation {
+        capacity: f64,
+        used: f64,
+        total_used: f64,
+        total_capacity: f64,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub(super) struct Reservations {
+        list: Vec<Reservation>,
+        preemption: Preemption,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Reservation {
+        order_id: usize,
+        material_id: usize,
+        priority: usize,
+        reserved: f64,
+        consumed: f64,
+        released: f64,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub(super) enum Scheduling {
+        Strict,
+        WeightedFair(Vec<f64>),
+        Aging { interval: f64 },
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Order {
+        id: usize,
+        product_id: usize,
+        product_amount: f64,
+        preferred_variant: usize,
+        user_id: usize,
+        substitution: Substitution,
+        for_stock: bool,
+        options: OrderOptions,
+        queued_at: f64,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct OrderOptions {
+        partial: bool,
+        split: bool,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Production {
+        order: Order,
+        variant_id: usize,
+        completes_at: f64,
+    }
+}
+
+/// Version 3: sub-orders did not know their parent. Materials, products, workstations,
+/// reservations and the scheduling were as in version 2.
+pub(super) mod v3 {
+    use super::v2::{Material, Product, Reservations, Scheduling, Workstation};
+    use crate::{COption, OrderState, Substitution};
+    use serde::{Deserialize, Serialize};
+    use std::collections::{BTreeMap, HashMap};
+
+    #[derive(Serialize, Deserialize)]
+    pub(in crate::persistence) struct Instance {
+        materials: BTreeMap<usize, Material>,
+        products: BTreeMap<usize, Product>,
+        next_material_id: usize,
+        next_product_id: usize,
+        production_queue: Vec<Vec<Order>>,
+        scheduling: Scheduling,
+        finished_products: Vec<Order>,
+        order_states: HashMap<usize, OrderState>,
+        next_order_id: usize,
+        in_production: Vec<Production>,
+        time: f64,
+        time_per_complexity: f64,
+        workstations: BTreeMap<usize, Workstation>,
+        next_workstation_id: usize,
+        reservations: Reservations,
+        missed_deadlines: Vec<MissedDeadline>,
+        verbose: usize,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Order {
+        id: usize,
+        product_id: usize,
+        product_amount: f64,
+        preferred_variant: usize,
+        user_id: usize,
+        substitution: Substitution,
+        for_stock: bool,
+        options: OrderOptions,
+        queued_at: f64,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct OrderOptions {
+        partial: bool,
+        split: bool,
+        priority: COption<usize>,
+        due: COption<f64>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct MissedDeadline {
+        order_id: usize,
+        product_id: usize,
+        user_id: usize,
+        due: f64,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Production {
+        order: Order,
+        variant_id: usize,
+        completes_at: f64,
+    }
+}
@@ -0,0 +1,147 @@
+/*
+* Copyright 2017-2019 Michal Mauser
+*
+* This program is free software: you can redistribute it and/or modify
+* it under the terms of the GNU Affero General Public License as published by
+* the Free Software Foundation, either version 3 of the License, or
+* (at your option) any later version.
+*
+* This program is distributed in the hope that it will be useful,
+* but WITHOUT ANY WARRANTY; without even the implied warranty of
+* MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+* GNU Affero General Public License for more details.
+*
+* You should have received a copy of the GNU Affero General Public License
+* along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Materials set aside for queued orders, so that later orders cannot use them up.
+
+use crate::{ComponentKind, Materials, ProductVariant};
+use serde::{Deserialize, Serialize};
+
+/// What happened to the material reserved for an order.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct Reservation {
+    pub order_id: usize,
+    pub material_id: usize,
+    /// Priority of the order when it reserved the material.
+    pub priority: usize,
+    /// Still held for the order.
+    pub reserved: f64,
+    /// Used by the order, reserved or not.
+    pub consumed: f64,
+    /// Given back when the order was cancelled, made from another variant or preempted.
+    pub released: f64,
+}
+
+/// Whose reservations an order may use when there is not enough unreserved supply.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub enum Preemption {
+    #[default]
+    Never,
+    /// Those of orders with a lower priority, the lowest first.
+    HigherPriority,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub(crate) struct Reservations {
+    list: Vec<Reservation>,
+    pub(crate) preemption: Preemption,
+}
+
+impl Reservations {
+    pub(crate) fn get(&self, order_id: usize, material_id: usize) -> Option<&Reservation> {
+        self.list.iter().find(|r| r.order_id == order_id && r.material_id == material_id)
+    }
+
+    pub(crate) fn of_order(&self, order_id: usize) -> impl Iterator<Item = &Reservation> {
+        self.list.iter().filter(move |r| r.order_id == order_id)
+    }
+
+    fn entry(&mut self, order_id: usize, material_id: usize, priority: usize) -> &mut Reservation {
+        match self.list.iter().position(|r| r.order_id == order_id && r.material_id == material_id) {
+            Some(index) => &mut self.list[index],
+            None => {
+                self.list.push(Reservation { order_id, material_id, priority, ..Reservation::default() });
+                self.list.last_mut().unwrap()
+            }
+        }
+    }
+
+    /// Reserves what is available to promise, up to what making `amount` of the variant needs.
+    pub(crate) fn reserve(&mut self, materials: &mut Materials, order_id: usize, priority: usize, variant: &ProductVariant, amount: f64) {
+        for c in variant.components.iter().filter(|c| c.kind == ComponentKind::Material) {
+            let material = materials.get_mut(&c.id).unwrap();
+            let reservation = self.entry(order_id, c.id, priority);
+            let missing = c.amount * amount - reservation.reserved;
+            let reserved = missing.min(material.available_to_promise()).max(0.0);
+            reservation.reserved += reserved;
+            material.reserved += reserved;
+        }
+    }
+
+    // reserved for other orders the order may take
+    fn preemptible(&self, order_id: usize, priority: usize, material_id: usize) -> f64 {
+        match self.preemption {
+            Preemption::Never => 0.0,
+            Preemption::HigherPriority => self.list.iter()
+                .filter(|r| r.material_id == material_id && r.order_id != order_id && r.priority > priority)
+                .map(|r| r.reserved)
+                .sum(),
+        }
+    }
+
+    /// How much of the material the order can use.
+    pub(crate) fn available(&self, materials: &Materials, order_id: usize, priority: usize, material_id: usize) -> f64 {
+        let own = self.get(order_id, material_id).map_or(0.0, |r| r.reserved);
+        materials[&material_id].available_to_promise() + own + self.preemptible(order_id, priority, material_id)
+    }
+
+    /// Accounts for the order consuming `amount` of the variant: its own reservation is used
+    /// first, then unreserved supply, then what it may preempt.
+    pub(crate) fn consume(&mut self, materials: &mut Materials, order_id: usize, priority: usize, variant: &ProductVariant, amount: f64) {
+        for c in variant.components.iter().filter(|c| c.kind == ComponentKind::Material) {
+            let material = materials.get_mut(&c.id).unwrap();
+            let needed = c.amount * amount;
+            let reservation = self.entry(order_id, c.id, priority);
+            let own = reservation.reserved.min(needed);
+            reservation.reserved -= own;
+            reservation.consumed += needed;
+            material.reserved -= own;
+
+            let mut missing = needed - own - material.available_to_promise().max(0.0);
+            if missing <= 0.0 || self.preemption == Preemption::Never { continue; }
+            let mut victims: Vec<&mut Reservation> = self.list.iter_mut()
+                .filter(|r| r.material_id == c.id && r.order_id != order_id && r.priority > priority && r.reserved > 0.0)
+                .collect();
+            victims.sort_by(|a, b| b.priority.cmp(&a.priority).then(b.order_id.cmp(&a.order_id)));
+            for victim in victims {
+                let taken = victim.reserved.min(missing);
+                victim.reserved -= taken;
+                victim.released += taken;
+                material.reserved -= taken;
+                missing -= taken;
+                if missing <= 0.0 { break; }
+            }
+        }
+    }
+
+    /// Gives back everything still reserved for the order; its entries stay in the ledger
+    /// holding nothing, so they no longer count against what is available to promise.
+    pub(crate) fn release(&mut self, materials: &mut Materials, order_id: usize) {
+        for reservation in self.list.iter_mut().filter(|r| r.order_id == order_id && r.reserved > 0.0) {
+            if let Some(material) = materials.get_mut(&reservation.material_id) {
+                material.reserved -= reservation.reserved;
+            }
+            reservation.released += reservation.reserved;
+            reservation.reserved = 0.0;
+        }
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &Reservation> {
+        self.list.iter()
+    }
+}
@@ -0,0 +1,98 @@
+/*
+* Copyright 2017-2019 Michal Mauser
+*
+* This program is free software: you can redistribute it and/or modify
+* it under the terms of the GNU Affero General Public License as published by
+* the Free Software Foundation, either version 3 of the License, or
+* (at your option) any later version.
+*
+* This program is distributed in the hope that it will be useful,
+* but WITHOUT ANY WARRANTY; without even the implied warranty of
+* MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+* GNU Affero General Public License for more details.
+*
+* You should have received a copy of the GNU Affero General Public License
+* along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! How scarce a material is, given its demand and supply.
+
+use crate::{Material, EQUILIBRIUM};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// Scarcities of all materials are compared with each other when choosing variants,
+/// so models should keep `EQUILIBRIUM` meaning demand in balance with supply.
+pub trait ScarcityModel: Send + Sync {
+    /// Materials select their model by name, which is also what gets saved.
+    fn name(&self) -> &str;
+
+    fn scarcity(&self, material: &Material) -> f64;
+
+    /// Variants made of scarce materials are avoided and subscribers are told about them.
+    fn is_scarce(&self, material: &Material) -> bool {
+        self.scarcity(material) > EQUILIBRIUM
+    }
+}
+
+/// The default model: scarcity grows with demand and falls with supply and deposit,
+/// demand equal to both together is the `EQUILIBRIUM`. Overharvested renewable
+/// materials are scarce in proportion to how much they are overharvested.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Proportional;
+
+impl ScarcityModel for Proportional {
+    fn name(&self) -> &str {
+        "proportional"
+    }
+
+    fn scarcity(&self, material: &Material) -> f64 { //100/2=50
+        let scarcity = if material.remaining() != 0.0 {
+            material.demand * EQUILIBRIUM / material.remaining()
+        } else { f64::INFINITY };
+        match material.regeneration {
+            Some(r) if material.is_overharvested() => scarcity.max(material.harvest_rate * EQUILIBRIUM / r.rate),
+            _ => scarcity,
+        }
+    }
+}
+
+/// The model a material uses; loaded materials start with `Proportional` until
+/// the model they selected is added to the instance.
+#[derive(Clone)]
+pub(crate) struct ModelRef(pub(crate) Arc<dyn ScarcityModel>);
+
+impl Default for ModelRef {
+    fn default() -> Self {
+        ModelRef(Arc::new(Proportional))
+    }
+}
+
+impl fmt::Debug for ModelRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.0.name())
+    }
+}
+
+/// Models known to an instance; copies of the instance share them.
+#[derive(Clone, Default)]
+pub(crate) struct Models {
+    pub(crate) default: ModelRef,
+    named: HashMap<String, ModelRef>,
+}
+
+impl Models {
+    pub(crate) fn add(&mut self, model: ModelRef) {
+        self.named.insert(model.0.name().to_string(), model);
+    }
+
+    /// `Proportional` is always known, even when replaced as the default.
+    pub(crate) fn get(&self, name: &str) -> Option<ModelRef> {
+        match self.named.get(name) {
+            Some(model) => Some(model.clone()),
+            None if name == Proportional.name() => Some(ModelRef::default()),
+            None => None,
+        }
+    }
+}
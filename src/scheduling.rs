@@ -0,0 +1,55 @@
+/*
+* Copyright 2017-2019 Michal Mauser
+*
+* This program is free software: you can redistribute it and/or modify
+* it under the terms of the GNU Affero General Public License as published by
+* the Free Software Foundation, either version 3 of the License, or
+* (at your option) any later version.
+*
+* This program is distributed in the hope that it will be useful,
+* but WITHOUT ANY WARRANTY; without even the implied warranty of
+* MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+* GNU Affero General Public License for more details.
+*
+* You should have received a copy of the GNU Affero General Public License
+* along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! In which order the priority levels of the production queue get to their orders.
+
+use crate::Order;
+use serde::{Deserialize, Serialize};
+
+/// Levels are numbered from 0, the most urgent.
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub enum Scheduling {
+    /// All orders of a level before any of the next one.
+    #[default]
+    Strict,
+    /// Levels take turns in proportion to their weights, one weight per level.
+    WeightedFair(Vec<f64>),
+    /// Levels move up one for every `interval` units of time their longest waiting order has waited.
+    Aging { interval: f64 },
+}
+
+impl Scheduling {
+    /// The level to take the next order from; `cursors` point at the next order of
+    /// every level, `visits` counts the orders taken from it so far.
+    pub(crate) fn next_level(&self, queue: &[Vec<Order>], cursors: &[usize], visits: &[usize], time: f64) -> Option<usize> {
+        let mut waiting = (0..queue.len()).filter(|l| cursors[*l] < queue[*l].len());
+        match self {
+            Scheduling::Strict => waiting.next(),
+            Scheduling::WeightedFair(weights) => waiting.min_by(|a, b| {
+                let a_turn = (visits[*a] + 1) as f64 / weights[*a];
+                let b_turn = (visits[*b] + 1) as f64 / weights[*b];
+                a_turn.partial_cmp(&b_turn).unwrap().then(a.cmp(b))
+            }),
+            Scheduling::Aging { interval } => waiting.min_by_key(|l| {
+                // orders due sooner may have been queued ahead of older ones
+                let oldest = queue[*l][cursors[*l]..].iter().map(|o| o.queued_at).fold(f64::INFINITY, f64::min);
+                let waited = time - oldest;
+                (l.saturating_sub((waited / interval) as usize), *l)
+            }),
+        }
+    }
+}
@@ -0,0 +1,46 @@
+// Builds src/example.c against the generated header and the cdylib and runs it.
+#![cfg(unix)]
+
+use std::env;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[test]
+fn c_example() {
+    let manifest = Path::new(env!("CARGO_MANIFEST_DIR"));
+    // target/<profile>/deps/<test binary>
+    let lib_dir: PathBuf = env::current_exe().unwrap().parent().unwrap().parent().unwrap().into();
+    let binary = Path::new(env!("CARGO_TARGET_TMPDIR")).join("example");
+
+    let compiler = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let status = match Command::new(&compiler)
+        .arg("-Wall").arg("-Werror")
+        .arg("-I").arg(manifest.join("include"))
+        .arg(manifest.join("src/example.c"))
+        .arg("-L").arg(&lib_dir).arg("-lresource_mng")
+        .arg("-o").arg(&binary)
+        .status() {
+        Ok(status) => status,
+        Err(ref e) if e.kind() == ErrorKind::NotFound => {
+            eprintln!("no C compiler `{}`, skipping", compiler);
+            return;
+        }
+        Err(e) => panic!("{}", e),
+    };
+    assert!(status.success());
+
+    let output = Command::new(&binary)
+        .current_dir(env!("CARGO_TARGET_TMPDIR"))
+        .env("LD_LIBRARY_PATH", &lib_dir)
+        .env("DYLD_LIBRARY_PATH", &lib_dir)
+        .output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("1 (order 0)"), "{}", stdout);
+    assert!(stdout.contains("finished order 0: 2.000000 x product 0"), "{}", stdout);
+    assert!(stdout.contains("steel left: 0.000000"), "{}", stdout);
+    assert!(stdout.contains("manufactured: 1"), "{}", stdout);
+    assert!(stdout.contains("wood left: 8.000000"), "{}", stdout);
+    assert!(stdout.contains("loaded materials: 2"), "{}", stdout);
+}